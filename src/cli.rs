@@ -0,0 +1,432 @@
+//! Command line arguments.
+
+use std::path::PathBuf;
+use std::process;
+
+use macroquad::texture::FilterMode;
+use nokhwa::utils::FrameFormat;
+
+use crate::lut::{Interpolation, Position};
+use crate::pipeline::{MAX_SIZE, PassSpec, Scale};
+use crate::source::{Fallback, FormatRequest, Pattern, RawFormat};
+use crate::viewport::{Resolution, Scaling, Upscale};
+
+const USAGE: &str = "usage: shader-cam [options]
+
+input:
+    Input options can be repeated. The first input is bound to the shader as
+    `Texture`, later ones as `Texture1`, `Texture2`...
+
+    --camera <spec>     open a camera by index, name substring or /dev/videoN
+                        path (default 0)
+    --list-cameras      print the available cameras and their formats
+    --format <fmt>      capture format as [WxH][@fps][:fourcc], e.g.
+                        1920x1080@30:mjpeg or 640x480@60:yuyv
+    --fallback <mode>   used when --format is not offered exactly: closest,
+                        fps, resolution (default closest)
+    --cpu-convert       convert YUYV/NV12 camera frames on the CPU instead of
+                        in a shader
+    --image <path>      play a png/jpeg file or a directory of numbered frames
+    --video <path>      play a .y4m file, or a raw dump described by --raw-*
+    --raw-size <WxH>    frame size of a raw dump
+    --raw-format <fmt>  pixel layout of a raw dump: rgba, yuyv (default rgba)
+    --pattern <name>    generate a test pattern: bars, zoneplate, checker,
+                        gradient, counter
+    --size <WxH>        test pattern size (default 640x480)
+    --fps <n>           playback rate for file inputs (default 30, or the
+                        rate from the y4m header)
+    --loop              loop file inputs instead of holding the last frame
+
+effect:
+    Shaders can declare float uniforms as parameters with
+    `#pragma parameter NAME \"Label\" default min max step` lines, F1 shows
+    sliders to tune them. `#include \"file\"` pastes in a file next to the
+    shader, or one of the built-in snippets: color.glsl, noise.glsl,
+    curvature.glsl, blur.glsl.
+
+    --effect <name>     built-in effect to start with, Left/Right switch
+                        between them (default: the last one used)
+    --list-effects      print the built-in effects
+    --shader <path>     fragment shader to use instead of a built-in effect,
+                        reloaded whenever the file is saved. Repeat to chain
+                        passes, each samples the previous one as `Texture`,
+                        the first input as `Original` and earlier passes as
+                        `Pass0`, `Pass1`... Earlier frames of the output are
+                        `PrevFrame1`, `PrevFrame2`... up to 8 back, and of
+                        the first input `PrevInput1`... Shadertoy image
+                        shaders with mainImage() are accepted as is, with
+                        iChannel0 as the pass input and iChannel1-3 as the
+                        other inputs.
+                        A single ISF .fs shader runs with the passes, inputs
+                        and buffers declared in its header
+    --scale <scale>     output size of the preceding --shader pass: a
+                        multiple of its input like 0.5, a size like 320x240,
+                        or window (default 1, the last pass draws to the
+                        window)
+    --filter <mode>     how the preceding --shader pass samples its input:
+                        nearest, linear (default nearest)
+    --preset <path>     RetroArch .glslp shader preset to use instead of a
+                        built-in effect
+    --lut <path>        color grade with a 3D LUT from a .cube file or a Hald
+                        CLUT image
+    --lut-position <p>  grade before the effect or after it: before, after
+                        (default before)
+    --grade-image <input> <output>
+                        grade an image with --lut on the CPU and save it,
+                        without opening a window
+    --lut-interpolation <mode>
+                        interpolation for --grade-image: trilinear,
+                        tetrahedral (default tetrahedral)
+    --check-shader <path>
+                        check a shader file, or every .frag, .glsl and .fs
+                        file under a directory, without opening a window and
+                        exit with an error if any fails. Files without a
+                        main are #include snippets and skipped. Can be
+                        repeated
+
+window:
+    --window-size <WxH> initial size of the window (default 1280x720)
+    --fullscreen        start fullscreen, F11 toggles it at any time. This is
+                        a borderless window over the whole screen, so the
+                        display mode stays as it is
+    --no-resize         keep the window at its initial size
+    --high-dpi          render at the full pixel density of high DPI screens
+                        instead of having them scale the window up
+    --scaling <mode>    how the image fills the window: fit, fill (cropped),
+                        stretch, integer (whole multiples) (default fit)
+    --resolution <WxH>  run the effects at a fixed resolution like 320x240,
+                        with the inputs placed in it by --scaling
+    --resolution-scale <n>
+                        render the effects at this whole multiple of
+                        --resolution, e.g. for finer scanlines (default 1)
+    --upscale <filter>  how the output is scaled to the window: nearest,
+                        linear, sharp (sharp bilinear) (default nearest)
+
+    -h, --help          print this message
+";
+
+#[derive(Clone)]
+pub enum InputSpec {
+    /// Index, device path or name, see [`crate::source::find_camera`].
+    Camera(String),
+    Images(PathBuf),
+    Video(PathBuf),
+    Pattern(Pattern),
+}
+
+#[derive(Clone)]
+pub struct Args {
+    pub list_cameras: bool,
+    pub format: FormatRequest,
+    pub gpu_convert: bool,
+    /// Every input in command line order, at least one.
+    pub inputs: Vec<InputSpec>,
+    pub fps: Option<f32>,
+    pub looping: bool,
+    pub raw_size: Option<(u32, u32)>,
+    pub raw_format: RawFormat,
+    pub size: (u32, u32),
+    pub list_effects: bool,
+    pub effect: Option<String>,
+    /// Shader file passes in order, empty to use a built-in effect.
+    pub shaders: Vec<PassSpec>,
+    pub preset: Option<PathBuf>,
+    pub lut: Option<PathBuf>,
+    pub lut_position: Position,
+    /// Input and output image to grade instead of running.
+    pub grade_image: Option<(PathBuf, PathBuf)>,
+    pub lut_interpolation: Interpolation,
+    /// Shader files and directories to validate instead of running.
+    pub check_shaders: Vec<PathBuf>,
+    pub window_size: (u32, u32),
+    pub fullscreen: bool,
+    pub resizable: bool,
+    pub high_dpi: bool,
+    pub scaling: Scaling,
+    pub resolution: Option<Resolution>,
+    pub upscale: Upscale,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            list_cameras: false,
+            format: FormatRequest::default(),
+            gpu_convert: true,
+            inputs: Vec::new(),
+            fps: None,
+            looping: false,
+            raw_size: None,
+            raw_format: RawFormat::Rgba,
+            size: (640, 480),
+            list_effects: false,
+            effect: None,
+            shaders: Vec::new(),
+            preset: None,
+            lut: None,
+            lut_position: Position::Before,
+            grade_image: None,
+            lut_interpolation: Interpolation::Tetrahedral,
+            check_shaders: Vec::new(),
+            window_size: (1280, 720),
+            fullscreen: false,
+            resizable: true,
+            high_dpi: false,
+            scaling: Scaling::Fit,
+            resolution: None,
+            upscale: Upscale::Nearest,
+        }
+    }
+}
+
+pub fn parse() -> Args {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1);
+
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--camera" => args.inputs.push(InputSpec::Camera(value(&mut argv, &arg))),
+            "--list-cameras" => args.list_cameras = true,
+            "--format" => {
+                let v = value(&mut argv, &arg);
+                let fallback = args.format.fallback;
+                args.format = camera_format(&v)
+                    .unwrap_or_else(|| fail(&format!("{arg}: invalid format '{v}'")));
+                args.format.fallback = fallback;
+            }
+            "--fallback" => {
+                args.format.fallback = match value(&mut argv, &arg).as_str() {
+                    "closest" => Fallback::Closest,
+                    "fps" => Fallback::HighestFps,
+                    "resolution" => Fallback::HighestResolution,
+                    v => fail(&format!("{arg}: unknown mode '{v}'")),
+                }
+            }
+            "--cpu-convert" => args.gpu_convert = false,
+            "--image" => args
+                .inputs
+                .push(InputSpec::Images(value(&mut argv, &arg).into())),
+            "--video" => args
+                .inputs
+                .push(InputSpec::Video(value(&mut argv, &arg).into())),
+            "--raw-size" => args.raw_size = Some(size(&mut argv, &arg)),
+            "--raw-format" => {
+                args.raw_format = match value(&mut argv, &arg).as_str() {
+                    "rgba" => RawFormat::Rgba,
+                    "yuyv" => RawFormat::Yuyv,
+                    v => fail(&format!("{arg}: unknown format '{v}'")),
+                }
+            }
+            "--pattern" => {
+                let name = value(&mut argv, &arg);
+                let pattern = Pattern::from_name(&name)
+                    .unwrap_or_else(|| fail(&format!("{arg}: unknown pattern '{name}'")));
+                args.inputs.push(InputSpec::Pattern(pattern));
+            }
+            "--size" => args.size = size(&mut argv, &arg),
+            "--fps" => {
+                let fps: f32 = number(&mut argv, &arg);
+                if !(fps.is_finite() && fps > 0.0) {
+                    fail(&format!("{arg}: must be greater than 0"));
+                }
+                args.fps = Some(fps);
+            }
+            "--loop" => args.looping = true,
+            "--effect" => {
+                let name = value(&mut argv, &arg);
+                if crate::effects::find(&name).is_none() {
+                    fail(&format!(
+                        "{arg}: unknown effect '{name}', see --list-effects"
+                    ));
+                }
+                args.effect = Some(name);
+            }
+            "--list-effects" => args.list_effects = true,
+            "--shader" => args.shaders.push(PassSpec {
+                path: value(&mut argv, &arg).into(),
+                scale: None,
+                filter: FilterMode::Nearest,
+            }),
+            "--scale" => {
+                let v = value(&mut argv, &arg);
+                let scale =
+                    pass_scale(&v).unwrap_or_else(|| fail(&format!("{arg}: invalid scale '{v}'")));
+                last_pass(&mut args, &arg).scale = Some(scale);
+            }
+            "--filter" => {
+                let filter = match value(&mut argv, &arg).as_str() {
+                    "nearest" => FilterMode::Nearest,
+                    "linear" => FilterMode::Linear,
+                    v => fail(&format!("{arg}: unknown filter '{v}'")),
+                };
+                last_pass(&mut args, &arg).filter = filter;
+            }
+            "--preset" => args.preset = Some(value(&mut argv, &arg).into()),
+            "--lut" => args.lut = Some(value(&mut argv, &arg).into()),
+            "--lut-position" => {
+                args.lut_position = match value(&mut argv, &arg).as_str() {
+                    "before" => Position::Before,
+                    "after" => Position::After,
+                    v => fail(&format!("{arg}: unknown position '{v}'")),
+                }
+            }
+            "--grade-image" => {
+                let input = value(&mut argv, &arg).into();
+                args.grade_image = Some((input, value(&mut argv, &arg).into()));
+            }
+            "--lut-interpolation" => {
+                args.lut_interpolation = match value(&mut argv, &arg).as_str() {
+                    "trilinear" => Interpolation::Trilinear,
+                    "tetrahedral" => Interpolation::Tetrahedral,
+                    v => fail(&format!("{arg}: unknown interpolation '{v}'")),
+                }
+            }
+            "--check-shader" => args.check_shaders.push(value(&mut argv, &arg).into()),
+            "--window-size" => args.window_size = size(&mut argv, &arg),
+            "--fullscreen" => args.fullscreen = true,
+            "--no-resize" => args.resizable = false,
+            "--high-dpi" => args.high_dpi = true,
+            "--scaling" => {
+                args.scaling = match value(&mut argv, &arg).as_str() {
+                    "fit" => Scaling::Fit,
+                    "fill" => Scaling::Fill,
+                    "stretch" => Scaling::Stretch,
+                    "integer" => Scaling::Integer,
+                    v => fail(&format!("{arg}: unknown mode '{v}'")),
+                }
+            }
+            "--resolution" => {
+                let (width, height) = size(&mut argv, &arg);
+                let scale = args.resolution.map_or(1, |r| r.scale);
+                args.resolution = Some(Resolution {
+                    width,
+                    height,
+                    scale,
+                });
+            }
+            "--resolution-scale" => {
+                let scale: u32 = number(&mut argv, &arg);
+                if scale == 0 {
+                    fail(&format!("{arg}: must be at least 1"));
+                }
+                match &mut args.resolution {
+                    Some(r) => r.scale = scale,
+                    None => fail(&format!("{arg} must follow --resolution")),
+                }
+            }
+            "--upscale" => {
+                args.upscale = match value(&mut argv, &arg).as_str() {
+                    "nearest" => Upscale::Nearest,
+                    "linear" => Upscale::Linear,
+                    "sharp" => Upscale::SharpBilinear,
+                    v => fail(&format!("{arg}: unknown filter '{v}'")),
+                }
+            }
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                process::exit(0);
+            }
+            _ => fail(&format!("unknown argument '{arg}'")),
+        }
+    }
+
+    if let Some(r) = args.resolution
+        && r.width.max(r.height).saturating_mul(r.scale) > MAX_SIZE as u32
+    {
+        fail(&format!(
+            "--resolution-scale: the scaled size is larger than {MAX_SIZE}"
+        ));
+    }
+    if args.grade_image.is_some() && args.lut.is_none() {
+        fail("--grade-image needs a --lut");
+    }
+    if args.inputs.is_empty() {
+        args.inputs.push(InputSpec::Camera("0".to_string()));
+    }
+
+    args
+}
+
+fn value(argv: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    argv.next()
+        .unwrap_or_else(|| fail(&format!("{flag} expects a value")))
+}
+
+fn number<T: std::str::FromStr>(argv: &mut impl Iterator<Item = String>, flag: &str) -> T {
+    let v = value(argv, flag);
+    v.parse()
+        .unwrap_or_else(|_| fail(&format!("{flag}: '{v}' is not a valid number")))
+}
+
+/// Parses `[WxH][@fps][:fourcc]`.
+fn camera_format(v: &str) -> Option<FormatRequest> {
+    let mut request = FormatRequest::default();
+
+    let (rest, fourcc) = match v.split_once(':') {
+        Some((rest, fourcc)) => (rest, Some(fourcc)),
+        None => (v, None),
+    };
+    let (res, fps) = match rest.split_once('@') {
+        Some((res, fps)) => (res, Some(fps)),
+        None => (rest, None),
+    };
+
+    if !res.is_empty() {
+        let (w, h) = res.split_once('x')?;
+        request.resolution = Some((w.parse().ok()?, h.parse().ok()?));
+    }
+    if let Some(fps) = fps {
+        request.fps = Some(fps.parse().ok()?);
+    }
+    if let Some(fourcc) = fourcc {
+        request.fourcc = Some(match fourcc.to_ascii_lowercase().as_str() {
+            "mjpeg" | "mjpg" => FrameFormat::MJPEG,
+            "yuyv" | "yuy2" => FrameFormat::YUYV,
+            "nv12" => FrameFormat::NV12,
+            "gray" | "grey" => FrameFormat::GRAY,
+            "rgb" | "rawrgb" => FrameFormat::RAWRGB,
+            _ => return None,
+        });
+    }
+
+    Some(request)
+}
+
+/// The pass that `--scale` and `--filter` apply to.
+fn last_pass<'a>(args: &'a mut Args, flag: &str) -> &'a mut PassSpec {
+    args.shaders
+        .last_mut()
+        .unwrap_or_else(|| fail(&format!("{flag} must follow a --shader")))
+}
+
+/// Parses a factor, `WIDTHxHEIGHT` or `window`.
+fn pass_scale(v: &str) -> Option<(Scale, Scale)> {
+    if v == "window" {
+        return Some((Scale::Viewport(1.0), Scale::Viewport(1.0)));
+    }
+    if let Some((w, h)) = v.split_once('x') {
+        let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+        return (w > 0 && h > 0).then_some((Scale::Absolute(w), Scale::Absolute(h)));
+    }
+    let s: f32 = v.parse().ok().filter(|&s: &f32| s > 0.0 && s.is_finite())?;
+    Some((Scale::Source(s), Scale::Source(s)))
+}
+
+/// Parses `WIDTHxHEIGHT`, up to [`MAX_SIZE`] each.
+fn size(argv: &mut impl Iterator<Item = String>, flag: &str) -> (u32, u32) {
+    let v = value(argv, flag);
+    let max = MAX_SIZE as u32;
+    v.split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|&(w, h)| (1..=max).contains(&w) && (1..=max).contains(&h))
+        .unwrap_or_else(|| {
+            fail(&format!(
+                "{flag}: '{v}' is not a size like 640x480, up to {max}x{max}"
+            ))
+        })
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("ERROR: {msg}\n\n{USAGE}");
+    process::exit(1);
+}
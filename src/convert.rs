@@ -0,0 +1,210 @@
+//! CPU pixel format conversion.
+//!
+//! All YUV input is treated as BT.601. Limited range is the default, as that
+//! is what webcams and most y4m files produce.
+//!
+//! These are also the reference for the GPU conversion in [`crate::upload`],
+//! which has to produce the same output.
+
+use crate::source::{Frame, PixelFormat};
+
+/// Converts any frame to tightly packed RGBA.
+pub fn frame_to_rgba(frame: Frame) -> Vec<u8> {
+    let (w, h) = (frame.width, frame.height);
+    let mut rgba = Vec::new();
+    match frame.format {
+        PixelFormat::Rgba => return frame.data,
+        PixelFormat::Yuyv => yuyv_to_rgba(&frame.data, w, h, &mut rgba),
+        PixelFormat::Nv12 => nv12_to_rgba(&frame.data, w, h, &mut rgba),
+    }
+    rgba
+}
+
+/// Converts one YUV sample to RGB.
+pub fn yuv_to_rgb(y: u8, u: u8, v: u8, full_range: bool) -> [u8; 3] {
+    let (y, u, v) = (y as f32, u as f32 - 128.0, v as f32 - 128.0);
+    let (y, u, v) = if full_range {
+        (y, u, v)
+    } else {
+        (
+            (y - 16.0) * 255.0 / 219.0,
+            u * 255.0 / 224.0,
+            v * 255.0 / 224.0,
+        )
+    };
+
+    let r = y + 1.402 * v;
+    let g = y - 0.344136 * u - 0.714136 * v;
+    let b = y + 1.772 * u;
+    [clamp(r), clamp(g), clamp(b)]
+}
+
+fn clamp(x: f32) -> u8 {
+    x.round().clamp(0.0, 255.0) as u8
+}
+
+/// Packed 4:2:2 `Y0 U Y1 V` to RGBA.
+pub fn yuyv_to_rgba(src: &[u8], width: u32, height: u32, dst: &mut Vec<u8>) {
+    dst.clear();
+    dst.reserve((width * height * 4) as usize);
+
+    let stride = (width as usize).div_ceil(2) * 4;
+    for row in src.chunks_exact(stride).take(height as usize) {
+        for (i, px) in row.chunks_exact(4).enumerate() {
+            let [y0, u, y1, v] = [px[0], px[1], px[2], px[3]];
+            let [r, g, b] = yuv_to_rgb(y0, u, v, false);
+            dst.extend_from_slice(&[r, g, b, 255]);
+            if i * 2 + 1 < width as usize {
+                let [r, g, b] = yuv_to_rgb(y1, u, v, false);
+                dst.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
+
+/// Y plane plus interleaved half resolution UV plane to RGBA.
+pub fn nv12_to_rgba(src: &[u8], width: u32, height: u32, dst: &mut Vec<u8>) {
+    dst.clear();
+    dst.reserve((width * height * 4) as usize);
+
+    let (w, h) = (width as usize, height as usize);
+    let (luma, chroma) = src.split_at(w * h);
+    let stride = w.div_ceil(2) * 2;
+    for y in 0..h {
+        for x in 0..w {
+            let c = (y / 2) * stride + (x / 2) * 2;
+            let [r, g, b] = yuv_to_rgb(luma[y * w + x], chroma[c], chroma[c + 1], false);
+            dst.extend_from_slice(&[r, g, b, 255]);
+        }
+    }
+}
+
+/// Three separate planes to RGBA. The chroma planes are `cw` x `ch` and are
+/// sampled nearest-neighbour, so this covers 4:2:0, 4:2:2 and 4:4:4.
+pub struct Planes<'a> {
+    pub y: &'a [u8],
+    pub u: &'a [u8],
+    pub v: &'a [u8],
+    pub cw: u32,
+    pub ch: u32,
+}
+
+pub fn planar_to_rgba(
+    planes: &Planes,
+    width: u32,
+    height: u32,
+    full_range: bool,
+    dst: &mut Vec<u8>,
+) {
+    dst.clear();
+    dst.reserve((width * height * 4) as usize);
+
+    let (xs, ys) = (width.div_ceil(planes.cw), height.div_ceil(planes.ch));
+    for y in 0..height {
+        for x in 0..width {
+            let luma = planes.y[(y * width + x) as usize];
+            let c = ((y / ys) * planes.cw + x / xs) as usize;
+            let [r, g, b] = yuv_to_rgb(luma, planes.u[c], planes.v[c], full_range);
+            dst.extend_from_slice(&[r, g, b, 255]);
+        }
+    }
+}
+
+/// Greyscale to RGBA.
+pub fn luma_to_rgba(src: &[u8], full_range: bool, dst: &mut Vec<u8>) {
+    dst.clear();
+    dst.reserve(src.len() * 4);
+
+    for &luma in src {
+        let [r, g, b] = yuv_to_rgb(luma, 128, 128, full_range);
+        dst.extend_from_slice(&[r, g, b, 255]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: [u8; 3], b: [u8; 3]) {
+        assert!(
+            (0..3).all(|i| a[i].abs_diff(b[i]) <= 2),
+            "{a:?} is not near {b:?}"
+        );
+    }
+
+    #[test]
+    fn limited_range_colors() {
+        assert_eq!(yuv_to_rgb(16, 128, 128, false), [0, 0, 0]);
+        assert_eq!(yuv_to_rgb(235, 128, 128, false), [255, 255, 255]);
+        assert_near(yuv_to_rgb(81, 90, 240, false), [255, 0, 0]);
+        assert_near(yuv_to_rgb(145, 54, 34, false), [0, 255, 0]);
+        assert_near(yuv_to_rgb(41, 240, 110, false), [0, 0, 255]);
+    }
+
+    #[test]
+    fn full_range_colors() {
+        assert_eq!(yuv_to_rgb(0, 128, 128, true), [0, 0, 0]);
+        assert_eq!(yuv_to_rgb(255, 128, 128, true), [255, 255, 255]);
+        assert_near(yuv_to_rgb(76, 85, 255, true), [255, 0, 0]);
+    }
+
+    #[test]
+    fn yuyv_odd_width() {
+        // Three pixels wide, the last pair has one pixel.
+        let src = [16, 128, 235, 128, 235, 128, 0, 128];
+        let mut dst = Vec::new();
+        yuyv_to_rgba(&src, 3, 1, &mut dst);
+        assert_eq!(dst.len(), 3 * 4);
+        assert_eq!(dst[..4], [0, 0, 0, 255]);
+        assert_eq!(dst[4..8], [255, 255, 255, 255]);
+        assert_eq!(dst[8..], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn nv12_shares_chroma_per_block() {
+        // 4x2 luma, then one UV pair per 2x2 block: red on the left, blue on
+        // the right.
+        let mut src = vec![81, 81, 41, 41, 81, 81, 41, 41];
+        src.extend_from_slice(&[90, 240, 240, 110]);
+        let mut dst = Vec::new();
+        nv12_to_rgba(&src, 4, 2, &mut dst);
+        assert_eq!(dst.len(), 4 * 2 * 4);
+        for (i, px) in dst.chunks_exact(4).enumerate() {
+            let expected = if i % 4 < 2 { [255, 0, 0] } else { [0, 0, 255] };
+            assert_near([px[0], px[1], px[2]], expected);
+            assert_eq!(px[3], 255);
+        }
+    }
+
+    #[test]
+    fn planar_chroma_layouts() {
+        let y = [81, 41, 81, 41];
+        let mut dst = Vec::new();
+
+        // 4:2:0, one chroma sample for the 2x2 image.
+        let planes = Planes {
+            y: &y,
+            u: &[90],
+            v: &[240],
+            cw: 1,
+            ch: 1,
+        };
+        planar_to_rgba(&planes, 2, 2, false, &mut dst);
+        assert_near([dst[0], dst[1], dst[2]], [255, 0, 0]);
+        assert_eq!(dst.len(), 2 * 2 * 4);
+
+        // 4:4:4, red and blue columns.
+        let planes = Planes {
+            y: &y,
+            u: &[90, 240, 90, 240],
+            v: &[240, 110, 240, 110],
+            cw: 2,
+            ch: 2,
+        };
+        planar_to_rgba(&planes, 2, 2, false, &mut dst);
+        for (i, px) in dst.chunks_exact(4).enumerate() {
+            let expected = if i % 2 == 0 { [255, 0, 0] } else { [0, 0, 255] };
+            assert_near([px[0], px[1], px[2]], expected);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Built-in effects that can be switched between at runtime.
+
+use macroquad::texture::FilterMode;
+
+use crate::pipeline::Scale;
+
+/// Shows its input unchanged.
+pub const PASSTHROUGH: &str = include_str!("shaders/passthrough.frag");
+
+pub struct Effect {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub passes: &'static [EffectPass],
+    /// Values replacing the defaults of the `#pragma parameter`s the shaders
+    /// declare.
+    pub params: &'static [(&'static str, f32)],
+}
+
+pub struct EffectPass {
+    pub fragment: &'static str,
+    pub scale: Option<(Scale, Scale)>,
+    pub filter: FilterMode,
+}
+
+/// A pass at the size of its input, sampled with nearest filtering.
+const fn pass(fragment: &'static str) -> EffectPass {
+    EffectPass {
+        fragment,
+        scale: None,
+        filter: FilterMode::Nearest,
+    }
+}
+
+pub const EFFECTS: &[Effect] = &[
+    Effect {
+        name: "crt",
+        description: "Curved CRT screen with vignette, scanlines and aperture grille",
+        passes: &[pass(include_str!("shaders/crt.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "none",
+        description: "Camera image as is",
+        passes: &[pass(PASSTHROUGH)],
+        params: &[],
+    },
+    Effect {
+        name: "grayscale",
+        description: "Rec. 709 luma",
+        passes: &[pass(include_str!("shaders/grayscale.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "sepia",
+        description: "Old photograph tint",
+        passes: &[pass(include_str!("shaders/sepia.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "invert",
+        description: "Color negative",
+        passes: &[pass(include_str!("shaders/invert.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "pixelate",
+        description: "Large blocky pixels",
+        passes: &[pass(include_str!("shaders/pixelate.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "chromatic",
+        description: "Lens chromatic aberration towards the edges",
+        passes: &[pass(include_str!("shaders/chromatic.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "posterize",
+        description: "Reduced number of color levels",
+        passes: &[pass(include_str!("shaders/posterize.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "thermal",
+        description: "False color heat map of brightness",
+        passes: &[pass(include_str!("shaders/thermal.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "echo",
+        description: "Moving things leave fading copies behind",
+        passes: &[pass(include_str!("shaders/echo.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "denoise",
+        description: "Average of the last four frames where nothing moved",
+        passes: &[pass(include_str!("shaders/denoise.frag"))],
+        params: &[],
+    },
+    Effect {
+        name: "blur",
+        description: "Soft gaussian blur at half resolution",
+        passes: &[
+            EffectPass {
+                fragment: include_str!("shaders/blur_h.frag"),
+                scale: Some((Scale::Source(0.5), Scale::Source(0.5))),
+                filter: FilterMode::Linear,
+            },
+            pass(include_str!("shaders/blur_v.frag")),
+            EffectPass {
+                fragment: PASSTHROUGH,
+                scale: None,
+                filter: FilterMode::Linear,
+            },
+        ],
+        params: &[],
+    },
+    Effect {
+        name: "bloom",
+        description: "Bright areas glow into their surroundings",
+        passes: &[
+            EffectPass {
+                fragment: include_str!("shaders/bright.frag"),
+                scale: Some((Scale::Source(0.5), Scale::Source(0.5))),
+                filter: FilterMode::Linear,
+            },
+            pass(include_str!("shaders/blur_h.frag")),
+            pass(include_str!("shaders/blur_v.frag")),
+            EffectPass {
+                fragment: include_str!("shaders/bloom.frag"),
+                scale: None,
+                filter: FilterMode::Linear,
+            },
+        ],
+        params: &[("Radius", 0.03)],
+    },
+];
+
+pub fn find(name: &str) -> Option<usize> {
+    EFFECTS.iter().position(|e| e.name == name)
+}
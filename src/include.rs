@@ -0,0 +1,262 @@
+//! `#include "name"` for shaders, looked up next to the including file and
+//! then in a built-in library of snippets. `#include <name>` only looks in
+//! the library.
+//!
+//! Included text is pasted in place of the directive. Files wrapped in an
+//! include guard are still pasted again, where the guard hides them, but one
+//! including itself while it is open is skipped. Every line of the result
+//! remembers the file and line it came from, so errors point there.
+
+use std::path::{Path, PathBuf};
+
+/// The built-in snippets, by the name they are included as.
+pub const LIBRARY: &[(&str, &str)] = &[
+    ("blur.glsl", include_str!("shaders/include/blur.glsl")),
+    ("color.glsl", include_str!("shaders/include/color.glsl")),
+    (
+        "curvature.glsl",
+        include_str!("shaders/include/curvature.glsl"),
+    ),
+    ("noise.glsl", include_str!("shaders/include/noise.glsl")),
+];
+
+/// A shader with its includes pasted in.
+pub struct Expanded {
+    pub source: String,
+    /// Files the source came from, the shader itself first, and whether each
+    /// is from the library.
+    files: Vec<(PathBuf, bool)>,
+    /// Index into `files` and line in that file of each line of `source`.
+    lines: Vec<(usize, usize)>,
+}
+
+impl Expanded {
+    /// The shader itself.
+    pub fn path(&self) -> &Path {
+        &self.files[0].0
+    }
+
+    /// Files on disk the source came from, to watch for changes.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|(_, library)| !library)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// File and line that line `line` of `source` comes from, counting from
+    /// 1.
+    pub fn locate(&self, line: usize) -> Option<(&Path, usize)> {
+        let &(file, line) = self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file].0, line))
+    }
+}
+
+/// Expands the includes of `source`, the contents of `path`. Built-in shaders
+/// have no path and can only include from the library.
+pub fn expand(source: &str, path: Option<&Path>) -> Result<Expanded, String> {
+    let mut expanded = Expanded {
+        source: String::new(),
+        files: vec![(
+            path.unwrap_or(Path::new("built-in shader")).to_path_buf(),
+            path.is_none(),
+        )],
+        lines: Vec::new(),
+    };
+    paste(&mut expanded, source, 0, &mut Vec::new())?;
+    Ok(expanded)
+}
+
+/// Appends `source`, file `file` of `expanded`, with its includes. `open`
+/// holds the files being pasted around it.
+fn paste(
+    expanded: &mut Expanded,
+    source: &str,
+    file: usize,
+    open: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let (path, library) = expanded.files[file].clone();
+    open.push(canonical(&path));
+    for (i, line) in source.lines().enumerate() {
+        let Some((name, quoted)) = directive(line) else {
+            expanded.source += line;
+            expanded.source += "\n";
+            expanded.lines.push((file, i + 1));
+            continue;
+        };
+        let error = |message: String| format!("{}:{}: error: {message}", path.display(), i + 1);
+
+        // Files on disk can include their neighbours, the library can not.
+        let dir = path.parent().filter(|_| quoted && !library);
+        let (included, text, from_library) = match dir.map(|d| d.join(name)).filter(|p| p.is_file())
+        {
+            Some(p) => {
+                let text = std::fs::read_to_string(&p)
+                    .map_err(|e| error(format!("{}: {e}", p.display())))?;
+                (p, text, false)
+            }
+            None => match LIBRARY.iter().find(|(n, _)| *n == name) {
+                Some(&(n, text)) => (Path::new("built-in").join(n), text.to_string(), true),
+                None => return Err(error(format!("cannot find include \"{name}\""))),
+            },
+        };
+
+        if open.contains(&canonical(&included)) {
+            if !guarded(&text) {
+                return Err(error(format!(
+                    "include cycle through \"{name}\", which has no include guard"
+                )));
+            }
+            // Keeps the line numbers of what follows.
+            expanded.source += "\n";
+            expanded.lines.push((file, i + 1));
+            continue;
+        }
+        expanded.files.push((included, from_library));
+        let index = expanded.files.len() - 1;
+        paste(expanded, &text, index, open)?;
+    }
+    open.pop();
+    Ok(())
+}
+
+/// `path` in a form that is the same however it is reached.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The name in an `#include` line, and whether it is quoted rather than in
+/// angle brackets.
+fn directive(line: &str) -> Option<(&str, bool)> {
+    let rest = line.trim().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim();
+    if let Some(name) = rest.strip_prefix('"') {
+        Some((name.split_once('"')?.0, true))
+    } else {
+        Some((rest.strip_prefix('<')?.split_once('>')?.0, false))
+    }
+}
+
+/// Whether all of `source` is inside an `#ifndef NAME` `#define NAME` ...
+/// `#endif` guard.
+fn guarded(source: &str) -> bool {
+    // Lines other than blank ones and comments.
+    let code: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("//"))
+        .collect();
+    let (first, second) = match code.as_slice() {
+        [first, second, ..] => (words(first), words(second)),
+        _ => return false,
+    };
+    if !(first.len() == 2 && first[0] == "ifndef" && second.get(..2) == Some(&["define", first[1]]))
+    {
+        return false;
+    }
+    // The guard must only close on the last line.
+    let mut depth = 0;
+    for (i, line) in code.iter().enumerate() {
+        match words(line).first().copied() {
+            Some("if" | "ifdef" | "ifndef") => depth += 1,
+            Some("endif") => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return i == code.len() - 1;
+        }
+    }
+    false
+}
+
+/// The words of a directive line, empty for other lines.
+fn words(line: &str) -> Vec<&str> {
+    line.strip_prefix('#')
+        .map_or(Vec::new(), |d| d.split_whitespace().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str) -> &'static str {
+        LIBRARY.iter().find(|(n, _)| *n == name).unwrap().1
+    }
+
+    #[test]
+    fn lines_map_back_to_their_file() {
+        let source = "float before;\n#include <color.glsl>\nvoid main() {}\n";
+        let expanded = expand(source, None).unwrap();
+        let color = library("color.glsl").lines().count();
+        assert_eq!(expanded.source.lines().count(), 2 + color);
+
+        let shader = Path::new("built-in shader");
+        let snippet = Path::new("built-in").join("color.glsl");
+        assert_eq!(expanded.locate(1), Some((shader, 1)));
+        assert_eq!(expanded.locate(2), Some((snippet.as_path(), 1)));
+        assert_eq!(expanded.locate(1 + color), Some((snippet.as_path(), color)));
+        assert_eq!(expanded.locate(2 + color), Some((shader, 3)));
+        assert_eq!(expanded.locate(3 + color), None);
+        assert_eq!(expanded.locate(0), None);
+        // The library is not watched for changes.
+        assert!(expanded.files().is_empty());
+    }
+
+    #[test]
+    fn guarded_files_can_be_included_twice() {
+        let source = "#include <noise.glsl>\n#include \"noise.glsl\"\n";
+        let expanded = expand(source, None).unwrap();
+        assert_eq!(expanded.source.matches("#define NOISE_GLSL").count(), 2);
+    }
+
+    #[test]
+    fn missing_include() {
+        let error = expand("\n#include <nope.glsl>\n", None).err().unwrap();
+        assert_eq!(
+            error,
+            "built-in shader:2: error: cannot find include \"nope.glsl\""
+        );
+    }
+
+    #[test]
+    fn guard_detection() {
+        for (name, text) in LIBRARY {
+            assert!(guarded(text), "{name}");
+        }
+        assert!(guarded(
+            "// x\n#ifndef A\n#define A 1\n#if B\n#endif\n#endif\n"
+        ));
+        assert!(!guarded("int x;"));
+        assert!(!guarded("#ifndef A\n#define B\n#endif\n"));
+        assert!(!guarded("#ifndef A\n#define A\n#endif\nint x;\n"));
+    }
+
+    #[test]
+    fn guarded_self_include_is_skipped() {
+        let mut expanded = Expanded {
+            source: String::new(),
+            files: vec![(Path::new("built-in").join("blur.glsl"), true)],
+            lines: Vec::new(),
+        };
+        let source = format!("#include <blur.glsl>\n{}", library("blur.glsl"));
+        paste(&mut expanded, &source, 0, &mut Vec::new()).unwrap();
+        assert_eq!(expanded.source.matches("#define BLUR_GLSL").count(), 1);
+        assert_eq!(expanded.lines.len(), source.lines().count());
+    }
+
+    #[test]
+    fn unguarded_cycle_is_rejected() {
+        let dir = std::env::temp_dir().join(format!("shader-cam-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.glsl"), "#include \"b.glsl\"\n").unwrap();
+        std::fs::write(dir.join("b.glsl"), "int b;\n#include \"a.glsl\"\n").unwrap();
+        let path = dir.join("a.glsl");
+        let error = expand("#include \"b.glsl\"\n", Some(&path)).err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let error = error.unwrap();
+        assert!(error.ends_with("include cycle through \"a.glsl\", which has no include guard"));
+        assert!(error.contains("b.glsl:2:"), "{error}");
+    }
+}
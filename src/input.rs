@@ -0,0 +1,72 @@
+//! One video input: a source on its capture thread plus its GPU texture.
+
+use macroquad::prelude::*;
+
+use crate::cli::{Args, InputSpec};
+use crate::source::{
+    self, CameraSource, CaptureStats, FrameSource, ImageSource, PatternSource, SourceError,
+    ThreadedSource, VideoSource,
+};
+use crate::upload::Uploader;
+
+/// Frame size used while a source has never delivered a frame.
+const NO_SIGNAL_SIZE: (u32, u32) = (640, 480);
+
+pub struct Input {
+    source: ThreadedSource,
+    uploader: Uploader,
+}
+
+impl Input {
+    pub fn open(spec: &InputSpec, args: &Args) -> Self {
+        let (spec, args) = (spec.clone(), args.clone());
+        let gpu_convert = args.gpu_convert;
+        let source = ThreadedSource::spawn(move || open_source(&spec, &args), NO_SIGNAL_SIZE);
+
+        let (width, height) = source.resolution();
+        Self {
+            source,
+            uploader: Uploader::new(width, height, gpu_convert),
+        }
+    }
+
+    /// Uploads the latest frame, or animated static while the source is down.
+    pub fn update(&mut self, frame_index: u64) {
+        if self.source.error().is_some() {
+            let frame = source::no_signal_frame(self.source.resolution(), frame_index);
+            self.uploader.upload(frame);
+        } else if let Ok(Some(frame)) = self.source.next_frame() {
+            self.uploader.upload(frame);
+        }
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        self.uploader.texture()
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        self.source.stats()
+    }
+}
+
+fn open_source(spec: &InputSpec, args: &Args) -> Result<Box<dyn FrameSource>, SourceError> {
+    let fps = args.fps.unwrap_or(30.0);
+    Ok(match spec {
+        InputSpec::Camera(spec) => {
+            let index = source::find_camera(spec)?;
+            Box::new(CameraSource::open(index, &args.format, args.gpu_convert)?)
+        }
+        InputSpec::Images(path) => Box::new(ImageSource::open(path, fps, args.looping)?),
+        InputSpec::Video(path) => match args.raw_size {
+            Some(size) => Box::new(VideoSource::open_raw(
+                path,
+                args.raw_format,
+                size,
+                fps,
+                args.looping,
+            )?),
+            None => Box::new(VideoSource::open_y4m(path, args.fps, args.looping)?),
+        },
+        InputSpec::Pattern(pattern) => Box::new(PatternSource::new(*pattern, args.size, fps)),
+    })
+}
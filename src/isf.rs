@@ -0,0 +1,536 @@
+//! ISF (Interactive Shader Format) `.fs` shaders.
+//!
+//! An ISF shader starts with a JSON comment listing its inputs and passes.
+//! Inputs become uniforms set to their defaults, image inputs are bound to
+//! the inputs of shader-cam in order. Each entry of `PASSES` runs as one pass
+//! of the same file with `PASSINDEX` set, drawing into the buffer named by
+//! its `TARGET` that later passes, or the next frame for `PERSISTENT`
+//! buffers, can sample. Audio inputs are not supported, `FLOAT` buffers are
+//! 8 bit and a custom `.vs` vertex shader is ignored.
+
+use std::path::Path;
+
+use macroquad::prelude::{FilterMode, Vec2, Vec4, vec2};
+
+use crate::effects;
+use crate::json::Json;
+use crate::pipeline::{self, Binding, MAX_SIZE, Pass, Pipeline, Sampler, Scale};
+use crate::shader::{self, Layout, ParamValue, Shader};
+
+const PREAMBLE: &str = "#version 100
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying vec2 uv;
+
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec3 iResolution;
+uniform vec4 iDate;
+
+#define TIME iTime
+#define TIMEDELTA iTimeDelta
+#define FRAMEINDEX iFrame
+#define RENDERSIZE iResolution.xy
+#define DATE iDate
+
+// ISF puts the origin at the bottom left, textures here start at the top.
+vec2 isf_NormCoord() { return vec2(uv.x, 1.0 - uv.y); }
+#define isf_FragNormCoord isf_NormCoord()
+vec4 isf_FragCoordAt() { return vec4(isf_NormCoord() * RENDERSIZE, gl_FragCoord.zw); }
+";
+
+const HELPERS: &str = "
+vec4 IMG_NORM_PIXEL(sampler2D image, vec2 coord) {
+    return texture2D(image, vec2(coord.x, 1.0 - coord.y));
+}
+vec4 isf_Pixel(sampler2D image, vec2 size, vec2 coord) {
+    return IMG_NORM_PIXEL(image, coord / size);
+}
+#define IMG_THIS_PIXEL(image) IMG_NORM_PIXEL(image, isf_NormCoord())
+#define IMG_THIS_NORM_PIXEL(image) IMG_NORM_PIXEL(image, isf_NormCoord())
+";
+
+struct Header {
+    inputs: Vec<Input>,
+    passes: Vec<PassDecl>,
+    /// Names and paths of the `IMPORTED` images.
+    imported: Vec<(String, String)>,
+}
+
+struct Input {
+    name: String,
+    /// `None` for images.
+    value: Option<ParamValue>,
+    /// The GLSL type of the uniform.
+    glsl: &'static str,
+}
+
+struct PassDecl {
+    target: Option<String>,
+    persistent: bool,
+    width: Option<String>,
+    height: Option<String>,
+}
+
+/// Whether `source` starts with an ISF JSON header.
+pub fn is_isf(source: &str) -> bool {
+    header_text(source).is_some_and(|text| {
+        Json::parse(text).is_ok_and(|json| {
+            ["ISFVSN", "INPUTS", "PASSES"]
+                .iter()
+                .any(|key| json.get(key).is_some())
+        })
+    })
+}
+
+fn header_text(source: &str) -> Option<&str> {
+    let rest = source.trim_start().strip_prefix("/*")?;
+    let end = rest.find("*/")?;
+    Some(&rest[..end])
+}
+
+fn header(source: &str) -> Result<Header, String> {
+    let text = header_text(source).ok_or("missing ISF header")?;
+    let json = Json::parse(text).map_err(|e| format!("ISF header: {e}"))?;
+    let items = |key: &str| json.get(key).and_then(Json::as_array).unwrap_or_default();
+
+    let mut inputs = Vec::new();
+    for input in items("INPUTS") {
+        let name = input
+            .get("NAME")
+            .and_then(Json::as_str)
+            .ok_or("ISF input without a NAME")?;
+        let ty = input.get("TYPE").and_then(Json::as_str).unwrap_or_default();
+        let default = input.get("DEFAULT");
+        let number = || default.and_then(Json::as_f64).unwrap_or_default() as f32;
+        let floats = |n: usize| -> Vec<f32> {
+            let items = default.and_then(Json::as_array).unwrap_or_default();
+            (0..n)
+                .map(|i| items.get(i).and_then(Json::as_f64).unwrap_or_default() as f32)
+                .collect()
+        };
+        let (value, glsl) = match ty {
+            "image" => (None, "sampler2D"),
+            "float" => (Some(ParamValue::Float(number())), "float"),
+            "long" => (Some(ParamValue::Int(number() as i32)), "int"),
+            "bool" | "event" => {
+                let on = default.and_then(Json::as_bool).unwrap_or_default();
+                (Some(ParamValue::Int(on as i32)), "bool")
+            }
+            "color" => {
+                let c = floats(4);
+                (
+                    Some(ParamValue::Vec4(Vec4::new(c[0], c[1], c[2], c[3]))),
+                    "vec4",
+                )
+            }
+            "point2D" => {
+                let p = floats(2);
+                (Some(ParamValue::Vec2(vec2(p[0], p[1]))), "vec2")
+            }
+            _ => return Err(format!("ISF input '{name}': unsupported type '{ty}'")),
+        };
+        inputs.push(Input {
+            name: name.to_string(),
+            value,
+            glsl,
+        });
+    }
+
+    let mut passes: Vec<PassDecl> = items("PASSES")
+        .iter()
+        .map(|pass| {
+            let text = |key: &str| match pass.get(key)? {
+                Json::Number(n) => Some(n.to_string()),
+                value => value.as_str().map(str::to_string),
+            };
+            PassDecl {
+                target: pass
+                    .get("TARGET")
+                    .and_then(Json::as_str)
+                    .map(str::to_string),
+                persistent: pass.get("PERSISTENT").and_then(Json::as_bool) == Some(true),
+                width: text("WIDTH"),
+                height: text("HEIGHT"),
+            }
+        })
+        .collect();
+    if passes.is_empty() {
+        passes.push(PassDecl {
+            target: None,
+            persistent: false,
+            width: None,
+            height: None,
+        });
+    }
+
+    let imported = match json.get("IMPORTED") {
+        // Either an object keyed by name or an array with NAME members.
+        Some(Json::Object(members)) => members
+            .iter()
+            .filter_map(|(name, v)| Some((name.clone(), v.get("PATH")?.as_str()?.to_string())))
+            .collect(),
+        Some(Json::Array(items)) => items
+            .iter()
+            .filter_map(|v| {
+                let name = v.get("NAME")?.as_str()?;
+                Some((name.to_string(), v.get("PATH")?.as_str()?.to_string()))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(Header {
+        inputs,
+        passes,
+        imported,
+    })
+}
+
+/// Turns an ISF shader into a GLSL 100 fragment shader for pass
+/// `layout.pass`, returning it with the input uniforms and their defaults.
+/// Line numbers in compile errors match `source`.
+pub fn translate(
+    source: &str,
+    layout: &Layout,
+) -> Result<(String, Vec<(String, ParamValue)>), String> {
+    let header = header(source)?;
+
+    let mut out = format!("{PREAMBLE}#define PASSINDEX {}\n\n", layout.pass);
+    let mut images: Vec<&str> = Vec::new();
+    for input in &header.inputs {
+        if input.value.is_some() {
+            out += &format!("uniform {} {};\n", input.glsl, input.name);
+        } else {
+            images.push(&input.name);
+        }
+    }
+    let targets = header.passes.iter().filter_map(|p| p.target.as_deref());
+    for name in targets.chain(header.imported.iter().map(|(name, _)| name.as_str())) {
+        if !images.contains(&name) {
+            images.push(name);
+        }
+    }
+    for name in images {
+        out += &format!("uniform sampler2D {name};\nuniform vec2 {name}Size;\n");
+    }
+    out += HELPERS;
+    out += "#line 1\n";
+
+    let body = shader::strip_version(source);
+    let body = replace_ident(&body, "gl_FragCoord", "isf_FragCoordAt()");
+    let body = replace_ident(&body, "vv_FragNormCoord", "isf_FragNormCoord");
+    let body = replace_macro(&body, "IMG_SIZE", |image| format!("({image}Size"));
+    let body = replace_macro(&body, "IMG_PIXEL", |image| {
+        format!("isf_Pixel({image}, {image}Size")
+    });
+    out += &body;
+
+    let params = header
+        .inputs
+        .into_iter()
+        .filter_map(|input| Some((input.name, input.value?)))
+        .collect();
+    Ok((out, params))
+}
+
+/// Replaces every whole identifier `name`.
+fn replace_ident(source: &str, name: &str, with: &str) -> String {
+    let mut out = String::new();
+    let mut rest = source;
+    while let Some(i) = shader::find_ident(rest, name) {
+        out += &rest[..i];
+        out += with;
+        rest = &rest[i + name.len()..];
+    }
+    out + rest
+}
+
+/// Rewrites `NAME(image` with `with(image)` for the ISF macros that also
+/// need the size of the image.
+fn replace_macro(source: &str, name: &str, with: impl Fn(&str) -> String) -> String {
+    let ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::new();
+    let mut rest = source;
+    while let Some(i) = shader::find_ident(rest, name) {
+        out += &rest[..i];
+        let after = rest[i + name.len()..].trim_start_matches([' ', '\t']);
+        let Some(args) = after.strip_prefix('(') else {
+            out += name;
+            rest = &rest[i + name.len()..];
+            continue;
+        };
+        let args = args.trim_start_matches([' ', '\t']);
+        let len = args.find(|c: char| !ident(c)).unwrap_or(args.len());
+        out += &with(&args[..len]);
+        rest = &args[len..];
+    }
+    out + rest
+}
+
+/// Loads an ISF shader as a pipeline over `inputs` inputs.
+pub fn load(path: &Path, inputs: usize) -> Result<Pipeline, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let header = header(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let imported: Vec<String> = header.imported.iter().map(|(n, _)| n.clone()).collect();
+    let luts = header
+        .imported
+        .iter()
+        .map(|(_, file)| pipeline::load_lut(&dir.join(file), FilterMode::Linear))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Pass sizes may refer to the float inputs by name.
+    let vars: Vec<(String, f32)> = header
+        .inputs
+        .iter()
+        .filter_map(|input| match input.value {
+            Some(ParamValue::Float(v)) => Some((input.name.clone(), v)),
+            Some(ParamValue::Int(v)) => Some((input.name.clone(), v as f32)),
+            _ => None,
+        })
+        .collect();
+
+    let mut passes = Vec::new();
+    for (i, decl) in header.passes.iter().enumerate() {
+        let mut samplers = pipeline::samplers(i, inputs, &[], &imported);
+        let images = header.inputs.iter().filter(|input| input.value.is_none());
+        for (k, input) in images.enumerate() {
+            let n = if k < inputs { k } else { 0 };
+            samplers.push(Sampler::new(input.name.clone(), Binding::Input(n)));
+        }
+        for (j, pass) in header.passes.iter().enumerate() {
+            if let Some(target) = &pass.target {
+                let binding = if j < i {
+                    Binding::Pass(j)
+                } else {
+                    Binding::Feedback(j)
+                };
+                samplers.push(Sampler::new(target.clone(), binding));
+            }
+        }
+
+        let width = decl.width.clone().unwrap_or("$WIDTH".to_string());
+        let height = decl.height.clone().unwrap_or("$HEIGHT".to_string());
+        // Tried at a tiny and a typical size, to catch both a division by
+        // zero and sizes that only blow up on a real screen.
+        for expr in [&width, &height] {
+            for render in [vec2(1.0, 1.0), vec2(1920.0, 1080.0)] {
+                eval(expr, render, &vars)
+                    .and_then(|v| {
+                        if v > MAX_SIZE {
+                            Err(format!("{v} is larger than {MAX_SIZE}"))
+                        } else {
+                            Ok(v)
+                        }
+                    })
+                    .map_err(|e| format!("{}: pass {i} size '{expr}': {e}", path.display()))?;
+            }
+        }
+        let vars = vars.clone();
+        let size = move |render: Vec2| {
+            vec2(
+                eval(&width, render, &vars).unwrap_or(render.x),
+                eval(&height, render, &vars).unwrap_or(render.y),
+            )
+        };
+
+        let shader = Shader::from_file(path, effects::PASSTHROUGH, &pipeline::layout(i, &samplers));
+        let scale = Some((Scale::Source(1.0), Scale::Source(1.0)));
+        passes.push(
+            Pass::new(shader, samplers, scale, FilterMode::Linear)
+                .with_size(size)
+                .with_persistent(decl.persistent),
+        );
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    Pipeline::new(name.into_owned(), passes, luts)
+}
+
+/// Evaluates a pass size expression like `floor($WIDTH / 2.0)`. `$WIDTH` and
+/// `$HEIGHT` are the size the pipeline renders at, other `$name`s are inputs.
+fn eval(expr: &str, render: Vec2, vars: &[(String, f32)]) -> Result<f32, String> {
+    let mut parser = Expr {
+        bytes: expr.as_bytes(),
+        pos: 0,
+        render,
+        vars,
+    };
+    let value = parser.sum()?;
+    if parser.peek().is_some() {
+        return Err("unexpected characters".to_string());
+    }
+    if !value.is_finite() {
+        return Err("not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+struct Expr<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    render: Vec2,
+    vars: &'a [(String, f32)],
+}
+
+impl Expr<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() != Some(byte) {
+            return Err(format!("expected '{}'", byte as char));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn word(&mut self) -> &str {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.')
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default()
+    }
+
+    fn sum(&mut self) -> Result<f32, String> {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                Some(b'+') => {
+                    self.pos += 1;
+                    value += self.product()?;
+                }
+                Some(b'-') => {
+                    self.pos += 1;
+                    value -= self.product()?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f32, String> {
+        let mut value = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(b'*') => {
+                    self.pos += 1;
+                    value *= self.unary()?;
+                }
+                Some(b'/') => {
+                    self.pos += 1;
+                    value /= self.unary()?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f32, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let value = self.sum()?;
+                self.expect(b')')?;
+                Ok(value)
+            }
+            Some(b'$') => {
+                self.pos += 1;
+                let name = self.word().to_string();
+                match name.as_str() {
+                    "WIDTH" => Ok(self.render.x),
+                    "HEIGHT" => Ok(self.render.y),
+                    _ => self
+                        .vars
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|&(_, v)| v)
+                        .ok_or(format!("unknown variable '${name}'")),
+                }
+            }
+            Some(b) if b.is_ascii_digit() || b == b'.' => {
+                let word = self.word();
+                word.parse().map_err(|_| format!("invalid number '{word}'"))
+            }
+            Some(_) => {
+                let name = self.word().to_string();
+                self.expect(b'(')?;
+                let mut args = vec![self.sum()?];
+                while self.peek() == Some(b',') {
+                    self.pos += 1;
+                    args.push(self.sum()?);
+                }
+                self.expect(b')')?;
+                let arg = |i: usize| {
+                    args.get(i)
+                        .copied()
+                        .ok_or(format!("{name}() needs {} arguments", i + 1))
+                };
+                Ok(match name.as_str() {
+                    "floor" => arg(0)?.floor(),
+                    "ceil" => arg(0)?.ceil(),
+                    "round" => arg(0)?.round(),
+                    "abs" => arg(0)?.abs(),
+                    "sqrt" => arg(0)?.sqrt(),
+                    "min" => arg(0)?.min(arg(1)?),
+                    "max" => arg(0)?.max(arg(1)?),
+                    "pow" => arg(0)?.powf(arg(1)?),
+                    _ => return Err(format!("unknown function '{name}'")),
+                })
+            }
+            None => Err("unexpected end".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_size_expressions() {
+        let render = vec2(640.0, 480.0);
+        let vars = [("a".to_string(), 0.25)];
+        assert_eq!(eval("floor($WIDTH/3.0)", render, &vars), Ok(213.0));
+        assert_eq!(eval("$HEIGHT * 2 + 1", render, &vars), Ok(961.0));
+        assert_eq!(eval("max($a, 1)", render, &vars), Ok(1.0));
+        assert_eq!(eval("$WIDTH * (1 - $a)", render, &vars), Ok(480.0));
+    }
+
+    #[test]
+    fn eval_errors() {
+        let render = vec2(640.0, 480.0);
+        assert_eq!(
+            eval("$b + 1", render, &[]),
+            Err("unknown variable '$b'".to_string())
+        );
+        assert!(eval("$WIDTH / 0", render, &[]).is_err());
+        assert!(eval("0 / 0", render, &[]).is_err());
+        assert!(eval("sin(4)", render, &[]).is_err());
+        assert!(eval("$WIDTH $HEIGHT", render, &[]).is_err());
+    }
+}
@@ -0,0 +1,461 @@
+//! 3D color lookup tables for grading, from `.cube` files or Hald CLUT
+//! images.
+//!
+//! On the GPU the table is a strip of its blue slices side by side, each
+//! red across and green down, sampled with linear filtering in two slices
+//! and blended, which is trilinear interpolation. [`Lut3d::sample`] does the
+//! same on the CPU, or tetrahedral interpolation, to check results without a
+//! window.
+
+use std::path::Path;
+
+use macroquad::prelude::{
+    Camera, DrawTextureParams, FilterMode, RenderTarget, Texture2D, UniformDesc, UniformType,
+    WHITE, draw_texture_ex, gl_use_default_material, set_camera, set_default_camera,
+};
+
+use crate::shader::{Layout, Shader};
+use crate::uniforms::{PassInfo, Uniforms};
+use crate::upload::{sized_target, target_camera};
+
+const FRAGMENT: &str = include_str!("shaders/lut.frag");
+
+/// Largest table size, which keeps the strip within 4096 texels wide.
+pub const MAX_SIZE: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Trilinear,
+    Tetrahedral,
+}
+
+/// A cube of `size`³ colors, red changing fastest, then green, then blue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3d {
+    pub size: usize,
+    pub data: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Loads a `.cube` file, or any other path as a Hald CLUT image.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let result = if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("cube"))
+        {
+            std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Self::parse_cube(&text))
+        } else {
+            image::open(path)
+                .map_err(|e| e.to_string())
+                .and_then(|image| {
+                    let rgb = image.into_rgb8();
+                    Self::from_hald(rgb.width(), rgb.height(), rgb.as_raw())
+                })
+        };
+        result.map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parses an Adobe/Resolve `.cube` file. Tables with a domain other than
+    /// 0 to 1 are resampled to it.
+    pub fn parse_cube(text: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut domain = ([0.0f32; 3], [1.0f32; 3]);
+        let mut data = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: &str| format!("line {}: {msg}", i + 1);
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let numbers = || -> Result<Vec<f32>, String> {
+                rest.split_whitespace()
+                    .map(|n| {
+                        n.parse()
+                            .map_err(|_| error(&format!("'{n}' is not a number")))
+                    })
+                    .collect()
+            };
+            let triple = |numbers: Vec<f32>| -> Result<[f32; 3], String> {
+                numbers
+                    .try_into()
+                    .map_err(|_| error("expected three numbers"))
+            };
+
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(error("1D LUTs are not supported")),
+                "LUT_3D_SIZE" => {
+                    let n: usize = rest.trim().parse().map_err(|_| error("invalid size"))?;
+                    if !(2..=MAX_SIZE).contains(&n) {
+                        return Err(error(&format!("size must be between 2 and {MAX_SIZE}")));
+                    }
+                    size = Some(n);
+                }
+                "DOMAIN_MIN" => domain.0 = triple(numbers()?)?,
+                "DOMAIN_MAX" => domain.1 = triple(numbers()?)?,
+                "LUT_3D_INPUT_RANGE" => match numbers()?[..] {
+                    [min, max] => domain = ([min; 3], [max; 3]),
+                    _ => return Err(error("expected two numbers")),
+                },
+                k if k.starts_with(|c: char| c.is_ascii_uppercase()) => {}
+                _ => {
+                    let values: Vec<f32> = line
+                        .split_whitespace()
+                        .map(|n| {
+                            n.parse()
+                                .map_err(|_| error(&format!("'{n}' is not a number")))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    data.push(triple(values)?);
+                }
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if data.len() != size.pow(3) {
+            return Err(format!(
+                "expected {} entries for size {size}, found {}",
+                size.pow(3),
+                data.len()
+            ));
+        }
+        let lut = Self { size, data };
+        if domain == ([0.0; 3], [1.0; 3]) {
+            return Ok(lut);
+        }
+        // Bake the domain in so lookups always cover 0 to 1.
+        let (min, max) = domain;
+        let scale = |c: usize, x: f32| ((x - min[c]) / (max[c] - min[c])).clamp(0.0, 1.0);
+        let data = lut
+            .grid()
+            .map(|rgb| {
+                lut.sample(
+                    [scale(0, rgb[0]), scale(1, rgb[1]), scale(2, rgb[2])],
+                    Interpolation::Trilinear,
+                )
+            })
+            .collect();
+        Ok(Self { size, data })
+    }
+
+    /// Reads a Hald CLUT image of level `L`, `L³` pixels square, which is a
+    /// cube of size `L²` in row order.
+    pub fn from_hald(width: u32, height: u32, rgb: &[u8]) -> Result<Self, String> {
+        let level = (1..=16).find(|l| l * l * l == width);
+        let Some(level) = level.filter(|_| width == height) else {
+            return Err(format!(
+                "{width}x{height} is not a Hald CLUT, those are L³ pixels square"
+            ));
+        };
+        let size = (level * level) as usize;
+        let data = rgb
+            .chunks_exact(3)
+            .map(|p| [p[0], p[1], p[2]].map(|v| v as f32 / 255.0))
+            .collect::<Vec<[f32; 3]>>();
+        if !(2..=MAX_SIZE).contains(&size) || data.len() != size.pow(3) {
+            return Err(format!(
+                "Hald CLUT level must be between 2 and {}",
+                MAX_SIZE.isqrt()
+            ));
+        }
+        Ok(Self { size, data })
+    }
+
+    /// The input colors of the table entries, in table order.
+    fn grid(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        let n = self.size;
+        let step = 1.0 / (n - 1) as f32;
+        (0..n.pow(3)).map(move |i| {
+            [
+                (i % n) as f32 * step,
+                (i / n % n) as f32 * step,
+                (i / (n * n)) as f32 * step,
+            ]
+        })
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[r + self.size * (g + self.size * b)]
+    }
+
+    /// Looks up `rgb`, clamped to 0 to 1.
+    pub fn sample(&self, rgb: [f32; 3], interpolation: Interpolation) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let pos = rgb.map(|c| c.clamp(0.0, 1.0) * max);
+        let lo = pos.map(|p| (p.floor() as usize).min(self.size - 2));
+        let [fr, fg, fb] = [0, 1, 2].map(|i| pos[i] - lo[i] as f32);
+        let c = |r: usize, g: usize, b: usize| self.at(lo[0] + r, lo[1] + g, lo[2] + b);
+        // Weighted sum of table entries.
+        let sum = |terms: &[(f32, [f32; 3])]| -> [f32; 3] {
+            [0, 1, 2].map(|i| terms.iter().map(|(w, c)| w * c[i]).sum())
+        };
+
+        match interpolation {
+            Interpolation::Trilinear => sum(&[
+                ((1.0 - fr) * (1.0 - fg) * (1.0 - fb), c(0, 0, 0)),
+                (fr * (1.0 - fg) * (1.0 - fb), c(1, 0, 0)),
+                ((1.0 - fr) * fg * (1.0 - fb), c(0, 1, 0)),
+                (fr * fg * (1.0 - fb), c(1, 1, 0)),
+                ((1.0 - fr) * (1.0 - fg) * fb, c(0, 0, 1)),
+                (fr * (1.0 - fg) * fb, c(1, 0, 1)),
+                ((1.0 - fr) * fg * fb, c(0, 1, 1)),
+                (fr * fg * fb, c(1, 1, 1)),
+            ]),
+            // Interpolates within the one of the six tetrahedra along the
+            // black to white diagonal that holds the color.
+            Interpolation::Tetrahedral => {
+                let (first, second) = if fr > fg {
+                    if fg > fb {
+                        ((fr, c(1, 0, 0)), (fg, c(1, 1, 0)))
+                    } else if fr > fb {
+                        ((fr, c(1, 0, 0)), (fb, c(1, 0, 1)))
+                    } else {
+                        ((fb, c(0, 0, 1)), (fr, c(1, 0, 1)))
+                    }
+                } else if fb > fg {
+                    ((fb, c(0, 0, 1)), (fg, c(0, 1, 1)))
+                } else if fb > fr {
+                    ((fg, c(0, 1, 0)), (fb, c(0, 1, 1)))
+                } else {
+                    ((fg, c(0, 1, 0)), (fr, c(1, 1, 0)))
+                };
+                let smallest = fr.min(fg).min(fb);
+                sum(&[
+                    (1.0 - first.0, c(0, 0, 0)),
+                    (first.0 - second.0, first.1),
+                    (second.0 - smallest, second.1),
+                    (smallest, c(1, 1, 1)),
+                ])
+            }
+        }
+    }
+
+    /// Grades 8 bit RGBA pixels in place.
+    pub fn apply(&self, rgba: &mut [u8], interpolation: Interpolation) {
+        for pixel in rgba.chunks_exact_mut(4) {
+            let rgb = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0);
+            let graded = self.sample(rgb, interpolation);
+            for i in 0..3 {
+                pixel[i] = (graded[i] * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    /// The blue slices side by side as 8 bit RGBA, `size²` by `size` pixels.
+    pub fn atlas(&self) -> Vec<u8> {
+        let n = self.size;
+        let mut rgba = vec![255; n * n * n * 4];
+        for b in 0..n {
+            for g in 0..n {
+                for r in 0..n {
+                    let i = (g * n * n + b * n + r) * 4;
+                    let c = self
+                        .at(r, g, b)
+                        .map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8);
+                    rgba[i..i + 3].copy_from_slice(&c);
+                }
+            }
+        }
+        rgba
+    }
+}
+
+/// Grades `input` with `lut` on the CPU and saves it to `output`.
+pub fn grade_image(
+    lut: &Lut3d,
+    input: &Path,
+    output: &Path,
+    interpolation: Interpolation,
+) -> Result<(), String> {
+    let mut image = image::open(input)
+        .map_err(|e| format!("{}: {e}", input.display()))?
+        .into_rgba8();
+    lut.apply(&mut image, interpolation);
+    image
+        .save(output)
+        .map_err(|e| format!("{}: {e}", output.display()))
+}
+
+/// Where grading runs in the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    /// On the first input, so the effect sees graded colors.
+    Before,
+    /// On the output of the effect.
+    After,
+}
+
+/// The grading pass, which runs around the passes of any pipeline.
+pub struct Grade {
+    pub position: Position,
+    shader: Shader,
+    lut: Texture2D,
+    target: Option<RenderTarget>,
+}
+
+impl Grade {
+    pub fn new(lut: &Lut3d, position: Position) -> Self {
+        let n = lut.size as u16;
+        let texture = Texture2D::from_rgba8(n * n, n, &lut.atlas());
+        texture.set_filter(FilterMode::Linear);
+        let layout = Layout {
+            textures: vec!["Lut".to_string()],
+            uniforms: vec![UniformDesc::new("LutSize", UniformType::Float2)],
+            ..Default::default()
+        };
+
+        Self {
+            position,
+            shader: Shader::builtin(FRAGMENT, &[], &layout),
+            lut: texture,
+            target: None,
+        }
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn shader_mut(&mut self) -> &mut Shader {
+        &mut self.shader
+    }
+
+    /// Grades `source` into a texture of the same size.
+    pub fn render(&mut self, source: &Texture2D, uniforms: &Uniforms) -> Texture2D {
+        let size = source.size();
+        let target = sized_target(&mut self.target, size);
+        let camera = target_camera(&target);
+        set_camera(&camera);
+        let info = PassInfo {
+            output: size,
+            input: size,
+            frame_count_mod: None,
+            mvp: camera.matrix(),
+        };
+        self.shader.apply(uniforms, &info);
+        self.shader.set_texture("Lut", &self.lut);
+        self.shader.set_uniform("LutSize", self.lut.size());
+        draw_texture_ex(
+            source,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+        set_default_camera();
+        target.texture
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `.cube` text for a table of `size` that maps each color with `f`.
+    fn cube(size: usize, header: &str, f: impl Fn([f32; 3]) -> [f32; 3]) -> String {
+        let lut = Lut3d {
+            size,
+            data: vec![[0.0; 3]; size.pow(3)],
+        };
+        let mut text = format!("LUT_3D_SIZE {size}\n{header}\n");
+        for rgb in lut.grid() {
+            let [r, g, b] = f(rgb);
+            text += &format!("{r} {g} {b}\n");
+        }
+        text
+    }
+
+    fn colors() -> impl Iterator<Item = [f32; 3]> {
+        (0..=10).flat_map(|r| {
+            (0..=10).flat_map(move |g| {
+                (0..=10).map(move |b| [r as f32 / 10.0, g as f32 / 10.0, b as f32 / 10.0])
+            })
+        })
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn identity_returns_input() {
+        let lut = Lut3d::parse_cube(&cube(5, "", |rgb| rgb)).unwrap();
+        for rgb in colors() {
+            assert_close(lut.sample(rgb, Interpolation::Trilinear), rgb);
+            assert_close(lut.sample(rgb, Interpolation::Tetrahedral), rgb);
+        }
+    }
+
+    #[test]
+    fn interpolations_agree_on_linear_table() {
+        let f = |[r, g, b]: [f32; 3]| {
+            [
+                0.5 * r + 0.25 * g,
+                g - 0.5 * b + 0.5,
+                0.1 + 0.3 * r + 0.6 * b,
+            ]
+        };
+        let lut = Lut3d::parse_cube(&cube(4, "", f)).unwrap();
+        for rgb in colors() {
+            let trilinear = lut.sample(rgb, Interpolation::Trilinear);
+            assert_close(trilinear, lut.sample(rgb, Interpolation::Tetrahedral));
+            assert_close(trilinear, f(rgb));
+        }
+    }
+
+    #[test]
+    fn domain_is_rescaled() {
+        let header = "DOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2";
+        let lut = Lut3d::parse_cube(&cube(5, header, |rgb| rgb)).unwrap();
+        assert_close(
+            lut.sample([0.5, 1.0, 0.0], Interpolation::Trilinear),
+            [0.25, 0.5, 0.0],
+        );
+
+        let lut = Lut3d::parse_cube(&cube(5, "LUT_3D_INPUT_RANGE 0.5 1", |rgb| rgb)).unwrap();
+        assert_close(
+            lut.sample([0.25, 0.75, 1.0], Interpolation::Trilinear),
+            [0.0, 0.5, 1.0],
+        );
+    }
+
+    #[test]
+    fn cube_errors() {
+        let mut text = cube(2, "", |rgb| rgb);
+        text.truncate(text.trim_end().rfind('\n').unwrap());
+        assert_eq!(
+            Lut3d::parse_cube(&text).unwrap_err(),
+            "expected 8 entries for size 2, found 7"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("0 0 0").unwrap_err(),
+            "missing LUT_3D_SIZE"
+        );
+        for size in ["1", "65", "x"] {
+            assert!(Lut3d::parse_cube(&format!("LUT_3D_SIZE {size}")).is_err());
+        }
+        assert!(Lut3d::parse_cube("LUT_3D_SIZE 2\n0 0").is_err());
+        assert!(Lut3d::parse_cube("LUT_1D_SIZE 16").is_err());
+    }
+
+    #[test]
+    fn hald_must_be_a_cube_size() {
+        let pixels = |side: u32| vec![0; (side * side * 3) as usize];
+        assert_eq!(Lut3d::from_hald(8, 8, &pixels(8)).unwrap().size, 4);
+        assert!(Lut3d::from_hald(10, 10, &pixels(10)).is_err());
+        assert!(Lut3d::from_hald(8, 27, &pixels(8)).is_err());
+        assert!(Lut3d::from_hald(1, 1, &pixels(1)).is_err());
+        // Level 9 is a table of 81, above the maximum.
+        assert!(Lut3d::from_hald(729, 729, &pixels(729)).is_err());
+    }
+}
@@ -1,12 +1,8 @@
 #![allow(unused)]
 
 use macroquad::prelude::*;
-use nokhwa::{Camera, pixel_format::*, utils::*};
 
-const WIN_WIDTH: i32 = 1280;
-const WIN_HEIGHT: i32 = 720;
-
-const CRT_VERTEX_SHADER: &'static str = "#version 100
+const VERTEX_SHADER: &str = "#version 100
 attribute vec3 position;
 attribute vec2 texcoord;
 attribute vec4 color0;
@@ -24,114 +20,223 @@ void main() {
 }
 ";
 
-const CRT_FRAGMENT_SHADER: &'static str = r#"
-#version 100
-precision lowp float;
-
-varying vec4 color;
-varying vec2 uv;
-
-uniform sampler2D Texture;
-
-// https://www.shadertoy.com/view/XtlSD7
-vec2 CRTCurveUV(vec2 uv)
-{
-    uv = uv * 2.0 - 1.0;
-    vec2 offset = abs( uv.yx ) / vec2( 6.0, 4.0 );
-    uv = uv + uv * offset * offset;
-    uv = uv * 0.5 + 0.5;
-    return uv;
-}
-
-void DrawVignette( inout vec3 color, vec2 uv )
-{
-    float vignette = uv.x * uv.y * ( 1.0 - uv.x ) * ( 1.0 - uv.y );
-    vignette = clamp( pow( 16.0 * vignette, 0.3 ), 0.0, 1.0 );
-    color *= vignette;
-}
-
-
-void DrawScanline( inout vec3 color, vec2 uv )
-{
-    float iTime = 0.1;
-    float scanline 	= clamp( 0.95 + 0.05 * cos( 3.14 * ( uv.y + 0.008 * iTime ) * 240.0 * 1.0 ), 0.0, 1.0 );
-    float grille 	= 0.85 + 0.15 * clamp( 1.5 * cos( 3.14 * uv.x * 640.0 * 1.0 ), 0.0, 1.0 );
-    color *= scanline * grille * 1.2;
-}
-
-void main() {
-    vec2 crtUV = CRTCurveUV(uv);
-    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
-    if (crtUV.x < 0.0 || crtUV.x > 1.0 || crtUV.y < 0.0 || crtUV.y > 1.0)
-    {
-        res = vec3(0.0, 0.0, 0.0);
-    }
-    DrawVignette(res, crtUV);
-    DrawScanline(res, uv);
-    gl_FragColor = vec4(res, 1.0);
-
-}
-"#;
-
 macro_rules! info {
     ($($arg:tt)*) => {
         println!("INFO: {}", format!($($arg)*))
     }
 }
 
-fn window_conf() -> Conf {
+mod cli;
+mod convert;
+mod effects;
+mod include;
+mod input;
+mod isf;
+mod json;
+mod lut;
+mod params;
+mod pipeline;
+mod retroarch;
+mod shader;
+mod shadertoy;
+mod source;
+mod state;
+mod uniforms;
+mod upload;
+mod validate;
+mod viewport;
+
+use cli::Args;
+use effects::EFFECTS;
+use input::Input;
+use pipeline::Pipeline;
+use state::State;
+use uniforms::Uniforms;
+
+fn window_conf(args: &Args) -> Conf {
     Conf {
         window_title: "Shader cam".to_string(),
-        window_width: WIN_WIDTH,
-        window_height: WIN_HEIGHT,
+        window_width: args.window_size.0 as i32,
+        window_height: args.window_size.1 as i32,
+        fullscreen: args.fullscreen,
+        window_resizable: args.resizable,
+        high_dpi: args.high_dpi,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::None);
+fn main() {
+    let args = cli::parse();
+
+    if args.list_cameras {
+        source::list_cameras();
+        return;
+    }
+    if !args.check_shaders.is_empty() {
+        let ok = validate::check_files(&args.check_shaders);
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+    if let (Some((input, output)), Some(path)) = (&args.grade_image, &args.lut) {
+        let result = lut::Lut3d::load(path)
+            .and_then(|lut| lut::grade_image(&lut, input, output, args.lut_interpolation));
+        if let Err(e) = result {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.list_effects {
+        for effect in EFFECTS {
+            println!("{:<12} {}", effect.name, effect.description);
+        }
+        return;
+    }
 
-    let mut cam = Camera::new(CameraIndex::Index(0), format).unwrap();
-    cam.open_stream().unwrap();
+    macroquad::Window::from_config(window_conf(&args), run(args));
+}
 
-    let res = cam.resolution();
-    let mut tex = Texture2D::from_image(&Image {
-        bytes: vec![0; (res.width() * res.height() * 4) as usize], // dummy data
-        width: res.width() as u16,
-        height: res.height() as u16,
+async fn run(args: Args) {
+    let mut inputs: Vec<Input> = args
+        .inputs
+        .iter()
+        .map(|spec| Input::open(spec, &args))
+        .collect();
+    let mut show_stats = false;
+    let mut show_params = false;
+    let mut fullscreen = args.fullscreen;
+    let mut panel = params::Panel::default();
+    let mut frame_index: u64 = 0;
+
+    let mut state = State::load();
+    let mut effect = args
+        .effect
+        .as_deref()
+        .or(state.get("effect"))
+        .and_then(effects::find)
+        .unwrap_or(0);
+    let load_failed = |e| -> Pipeline {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    };
+    let isf = match args.shaders.as_slice() {
+        [spec] if spec.path.extension().is_some_and(|e| e == "fs") => Some(&spec.path),
+        _ => None,
+    };
+    let grade = args.lut.as_ref().map(|path| {
+        let lut = lut::Lut3d::load(path).unwrap_or_else(|e| {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        });
+        lut::Grade::new(&lut, args.lut_position)
     });
-    tex.set_filter(FilterMode::Nearest);
-
-    let material = load_material(
-        ShaderSource::Glsl {
-            vertex: CRT_VERTEX_SHADER,
-            fragment: CRT_FRAGMENT_SHADER,
-        },
-        Default::default(),
-    )
-    .unwrap();
+    // Cycling would throw away a shader loaded from a file.
+    let builtin = args.preset.is_none() && isf.is_none() && args.shaders.is_empty();
+    if builtin && args.effect.as_deref().and_then(effects::find).is_some() {
+        state.set("effect", EFFECTS[effect].name);
+    }
+    let mut pipeline = if let Some(path) = &args.preset {
+        retroarch::load_preset(path, inputs.len()).unwrap_or_else(load_failed)
+    } else if let Some(path) = isf {
+        // ISF shaders declare their own passes.
+        isf::load(path, inputs.len()).unwrap_or_else(load_failed)
+    } else if !args.shaders.is_empty() {
+        Pipeline::from_files(&args.shaders, inputs.len())
+    } else {
+        Pipeline::builtin(&EFFECTS[effect], inputs.len())
+    }
+    .with_grade(grade);
+    let mut effect_changed_at = get_time();
+    let mut uniforms = Uniforms::default();
+    let mut output = viewport::Output::new(args.scaling, args.resolution, args.upscale);
 
     loop {
         if is_key_pressed(KeyCode::Escape) {
             break;
         }
+        if is_key_pressed(KeyCode::F1) {
+            show_params = !show_params;
+        }
+        if is_key_pressed(KeyCode::F3) {
+            show_stats = !show_stats;
+        }
+        if is_key_pressed(KeyCode::F11) {
+            fullscreen = !fullscreen;
+            set_fullscreen(fullscreen);
+        }
+        // Out of the way on a projector, but needed for the sliders.
+        show_mouse(!fullscreen || show_params);
+        let step = if is_key_pressed(KeyCode::Right) {
+            1
+        } else if is_key_pressed(KeyCode::Left) {
+            EFFECTS.len() - 1
+        } else {
+            0
+        };
+        if step != 0 && builtin {
+            effect = (effect + step) % EFFECTS.len();
+            pipeline =
+                Pipeline::builtin(&EFFECTS[effect], inputs.len()).with_grade(pipeline.take_grade());
+            state.set("effect", EFFECTS[effect].name);
+            effect_changed_at = get_time();
+        }
 
-        let frame = cam.frame().unwrap();
-        let res = frame.resolution();
-        let rgba = frame.decode_image::<RgbAFormat>().unwrap();
+        for input in &mut inputs {
+            input.update(frame_index);
+        }
 
-        tex.update(&Image {
-            bytes: rgba.to_vec(),
-            width: res.width() as u16,
-            height: res.height() as u16,
-        });
+        pipeline.poll();
+        let textures: Vec<Texture2D> = inputs.iter().map(|i| i.texture().clone()).collect();
+        let viewport = output.viewport(textures[0].size());
+        uniforms.update(frame_index, viewport);
+        let textures = output.downsample(&textures, &uniforms);
+        pipeline.draw(&textures, &uniforms, viewport, &output);
+
+        if let Some(error) = pipeline.error() {
+            draw_error(error);
+        }
+        if get_time() - effect_changed_at < 3.0 {
+            let description = EFFECTS
+                .iter()
+                .find(|e| e.name == pipeline.name())
+                .map_or("", |e| e.description);
+            let text = format!("{}  {description}", pipeline.name());
+            draw_text(&text, 10.0, screen_height() - 20.0, 24.0, WHITE);
+        }
 
-        gl_use_material(&material);
-        clear_background(BLACK);
-        draw_texture(&tex, 0.0, 0.0, WHITE);
-        gl_use_default_material();
+        if show_params {
+            panel.draw(&mut pipeline);
+        }
+        if show_stats {
+            draw_text(&format!("{} fps", get_fps()), 10.0, 20.0, 20.0, GREEN);
+            for (i, input) in inputs.iter().enumerate() {
+                let stats = input.stats();
+                let text = format!(
+                    "input {i}: captured {}  dropped {}  duplicated {}",
+                    stats.captured, stats.dropped, stats.duplicated
+                );
+                draw_text(&text, 10.0, 40.0 + 20.0 * i as f32, 20.0, GREEN);
+            }
+        }
 
+        frame_index += 1;
         next_frame().await;
     }
 }
+
+/// Shows a multi line message at the bottom of the window.
+fn draw_error(text: &str) {
+    let lines: Vec<&str> = text.lines().collect();
+    let height = 20.0 * lines.len() as f32 + 10.0;
+    let top = screen_height() - height;
+
+    draw_rectangle(
+        0.0,
+        top,
+        screen_width(),
+        height,
+        Color::new(0.0, 0.0, 0.0, 0.75),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, 10.0, top + 20.0 * (i + 1) as f32, 20.0, RED);
+    }
+}
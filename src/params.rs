@@ -0,0 +1,159 @@
+//! Shader parameters declared with `#pragma parameter NAME "Label" default
+//! min max step` lines, the RetroArch convention, and the panel to tune them.
+//!
+//! Each parameter is set as the `float` uniform `NAME`, which the shader
+//! declares itself. Passes that declare the same name share one value.
+
+use macroquad::prelude::{
+    Color, GRAY, MouseButton, Rect, WHITE, draw_rectangle, draw_text, is_mouse_button_down,
+    is_mouse_button_pressed, measure_text, mouse_position, screen_width, vec2,
+};
+
+use crate::pipeline::Pipeline;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub label: String,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    /// The value currently set.
+    pub value: f32,
+}
+
+impl Parameter {
+    /// Rounds `value` to a multiple of `step` from `min`, within range.
+    fn snap(&self, value: f32) -> f32 {
+        let value = if self.step > 0.0 {
+            self.min + ((value - self.min) / self.step).round() * self.step
+        } else {
+            value
+        };
+        value.clamp(self.min, self.max)
+    }
+
+    /// Formats `value` with as many decimals as the step has.
+    fn format(&self, value: f32) -> String {
+        let decimals = (0..6)
+            .find(|&d| {
+                let scaled = self.step * 10f32.powi(d);
+                (scaled - scaled.round()).abs() < 1e-3
+            })
+            .unwrap_or(6) as usize;
+        format!("{value:.decimals$}")
+    }
+}
+
+/// The parameters `source` declares. Lines with missing or unparseable
+/// numbers are skipped, `min`, `max` and `step` default to a range around
+/// `default`.
+pub fn parse(source: &str) -> Vec<Parameter> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("#pragma parameter")?;
+            let (name, rest) = rest.trim().split_once(char::is_whitespace)?;
+            let (label, rest) = rest.trim().strip_prefix('"')?.split_once('"')?;
+            let numbers = rest
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<f32>, _>>()
+                .ok()?;
+            let default = *numbers.first()?;
+            let min = numbers.get(1).copied().unwrap_or(default.min(0.0));
+            let max = numbers.get(2).copied().unwrap_or(default.max(1.0));
+            Some(Parameter {
+                name: name.to_string(),
+                label: label.to_string(),
+                default,
+                min,
+                max: max.max(min),
+                step: numbers.get(3).copied().unwrap_or(0.0),
+                value: default,
+            })
+        })
+        .collect()
+}
+
+const PANEL_WIDTH: f32 = 320.0;
+const ROW_HEIGHT: f32 = 36.0;
+const MARGIN: f32 = 10.0;
+
+/// Sliders for the parameters of a pipeline in the top right corner. Drag
+/// to change a value, right click to reset it.
+#[derive(Default)]
+pub struct Panel {
+    /// Name of the parameter being dragged.
+    dragging: Option<String>,
+}
+
+impl Panel {
+    pub fn draw(&mut self, pipeline: &mut Pipeline) {
+        let params = pipeline.parameters();
+        let left = screen_width() - PANEL_WIDTH - MARGIN;
+        let height = if params.is_empty() {
+            ROW_HEIGHT
+        } else {
+            ROW_HEIGHT * params.len() as f32
+        };
+        draw_rectangle(
+            left,
+            MARGIN,
+            PANEL_WIDTH,
+            height + MARGIN,
+            Color::new(0.0, 0.0, 0.0, 0.75),
+        );
+        if params.is_empty() {
+            draw_text("no parameters", left + MARGIN, MARGIN + 24.0, 20.0, GRAY);
+            return;
+        }
+
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.dragging = None;
+        }
+        let mouse = vec2(mouse_position().0, mouse_position().1);
+
+        for (i, param) in params.iter().enumerate() {
+            let top = MARGIN + ROW_HEIGHT * i as f32;
+            let bar = Rect::new(left + MARGIN, top + 24.0, PANEL_WIDTH - 2.0 * MARGIN, 6.0);
+            // Generous hit area around the thin bar.
+            let hit = Rect::new(bar.x, bar.y - 8.0, bar.w, bar.h + 16.0);
+
+            if hit.contains(mouse) {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.dragging = Some(param.name.clone());
+                }
+                if is_mouse_button_pressed(MouseButton::Right) {
+                    pipeline.set_parameter(&param.name, param.default);
+                }
+            }
+            let mut value = param.value;
+            if self.dragging.as_ref() == Some(&param.name) {
+                let t = ((mouse.x - bar.x) / bar.w).clamp(0.0, 1.0);
+                value = param.snap(param.min + t * (param.max - param.min));
+                if value != param.value {
+                    pipeline.set_parameter(&param.name, value);
+                }
+            }
+
+            draw_text(&param.label, bar.x, top + 18.0, 20.0, WHITE);
+            let text = param.format(value);
+            let width = measure_text(&text, None, 20, 1.0).width;
+            draw_text(&text, bar.x + bar.w - width, top + 18.0, 20.0, WHITE);
+
+            let range = (param.max - param.min).max(f32::EPSILON);
+            let t = ((value - param.min) / range).clamp(0.0, 1.0);
+            draw_rectangle(bar.x, bar.y, bar.w, bar.h, GRAY);
+            draw_rectangle(bar.x, bar.y, bar.w * t, bar.h, WHITE);
+            draw_rectangle(
+                bar.x + bar.w * t - 2.0,
+                bar.y - 4.0,
+                4.0,
+                bar.h + 8.0,
+                WHITE,
+            );
+        }
+    }
+}
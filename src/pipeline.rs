@@ -0,0 +1,543 @@
+//! Ordered chain of shader passes, each drawing into its own render target.
+//!
+//! Every pass samples the previous pass output as `Texture`, the first input
+//! as `Original`, the output of earlier pass N as `PassN` and the other inputs
+//! as `Texture1`, `Texture2`... The RetroArch names for the same textures are
+//! available too, see [`samplers`]. Earlier frames are `PrevFrame1`, `PrevFrame2`...
+//! for the pipeline output and `PrevInput1`, `PrevInput2`... for the first
+//! input, up to [`HISTORY`] frames back. The size of each sampler is in a
+//! `vec2` uniform named after it, `OriginalSize`, `Pass0Size` or, for the
+//! RetroArch names, `OrigTextureSize` and `OrigInputSize`.
+
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+
+use crate::effects::{self, Effect};
+use crate::lut::{Grade, Position};
+use crate::params::Parameter;
+use crate::shader::{Layout, Shader};
+use crate::uniforms::{PassInfo, Uniforms};
+use crate::upload::{sized_target, target_camera};
+use crate::viewport::{self, Output};
+
+/// Number of earlier frames shaders can sample.
+pub const HISTORY: usize = 8;
+
+/// Largest pass width or height, which GPUs generally support as a texture
+/// size.
+pub const MAX_SIZE: f32 = 8192.0;
+
+/// Size of a pass output along one axis.
+#[derive(Clone, Copy, Debug)]
+pub enum Scale {
+    /// Multiple of the pass input, the previous pass output.
+    Source(f32),
+    /// Multiple of the size of the image in the window.
+    Viewport(f32),
+    Absolute(u32),
+}
+
+impl Scale {
+    /// In whole pixels, between 1 and [`MAX_SIZE`].
+    fn size(self, source: f32, viewport: f32) -> f32 {
+        let size = match self {
+            Scale::Source(s) => source * s,
+            Scale::Viewport(s) => viewport * s,
+            Scale::Absolute(n) => n as f32,
+        };
+        if size.is_nan() {
+            return 1.0;
+        }
+        size.round().clamp(1.0, MAX_SIZE)
+    }
+}
+
+/// A shader file to run as one pass, see `--shader`.
+#[derive(Clone)]
+pub struct PassSpec {
+    pub path: PathBuf,
+    pub scale: Option<(Scale, Scale)>,
+    pub filter: FilterMode,
+}
+
+/// What a sampler is bound to.
+#[derive(Clone, Copy, Debug)]
+pub enum Binding {
+    Input(usize),
+    Pass(usize),
+    /// Output of a pass in the previous frame.
+    Feedback(usize),
+    /// Pipeline output this many frames back.
+    PrevFrame(usize),
+    /// First input this many frames back.
+    PrevInput(usize),
+    Lut(usize),
+}
+
+pub struct Sampler {
+    pub name: String,
+    pub binding: Binding,
+}
+
+impl Sampler {
+    pub fn new(name: impl Into<String>, binding: Binding) -> Self {
+        Self {
+            name: name.into(),
+            binding,
+        }
+    }
+
+    /// The `vec2` uniforms set to the size of the texture.
+    fn size_uniforms(&self) -> Vec<String> {
+        match self.name.strip_suffix("Texture") {
+            Some(prefix) => vec![format!("{prefix}TextureSize"), format!("{prefix}InputSize")],
+            None => vec![format!("{}Size", self.name)],
+        }
+    }
+}
+
+/// Samplers available to pass `index` besides `Texture`. `aliases` are the
+/// RetroArch names of the passes and `luts` the names of the lookup textures.
+pub fn samplers(
+    index: usize,
+    inputs: usize,
+    aliases: &[Option<String>],
+    luts: &[String],
+) -> Vec<Sampler> {
+    let mut samplers = vec![Sampler::new("Original", Binding::Input(0))];
+    samplers.extend((0..index).map(|i| Sampler::new(format!("Pass{i}"), Binding::Pass(i))));
+    samplers.extend((1..inputs).map(|i| Sampler::new(format!("Texture{i}"), Binding::Input(i))));
+
+    // RetroArch counts passes from 1 with the input as pass 0, and
+    // `PassPrevN` is N passes back from this one.
+    let retroarch = |n: usize| match n {
+        0 => Binding::Input(0),
+        n => Binding::Pass(n - 1),
+    };
+    samplers.push(Sampler::new("OrigTexture", Binding::Input(0)));
+    samplers.extend((1..=index).map(|n| Sampler::new(format!("Pass{n}Texture"), retroarch(n))));
+    samplers.extend(
+        (1..=index + 1)
+            .map(|n| Sampler::new(format!("PassPrev{n}Texture"), retroarch(index + 1 - n))),
+    );
+    samplers.push(Sampler::new("PrevFrame", Binding::PrevFrame(1)));
+    for n in 1..=HISTORY {
+        samplers.push(Sampler::new(format!("PrevFrame{n}"), Binding::PrevFrame(n)));
+        samplers.push(Sampler::new(format!("PrevInput{n}"), Binding::PrevInput(n)));
+    }
+    samplers.push(Sampler::new("PrevTexture", Binding::PrevInput(1)));
+    samplers.extend(
+        (1..HISTORY).map(|n| Sampler::new(format!("Prev{n}Texture"), Binding::PrevInput(n + 1))),
+    );
+    for (i, alias) in aliases.iter().enumerate().take(index) {
+        if let Some(alias) = alias {
+            samplers.push(Sampler::new(format!("{alias}Texture"), Binding::Pass(i)));
+        }
+    }
+    samplers.extend(
+        luts.iter()
+            .enumerate()
+            .map(|(i, name)| Sampler::new(name.clone(), Binding::Lut(i))),
+    );
+    samplers
+}
+
+pub struct Pass {
+    shader: Shader,
+    samplers: Vec<Sampler>,
+    /// `None` draws straight to the screen if this is the last pass, and
+    /// means `Source(1.0)` otherwise.
+    scale: Option<(Scale, Scale)>,
+    /// How this pass samples its input.
+    filter: FilterMode,
+    /// Output size from the size the pipeline renders at, the fixed
+    /// resolution or the viewport, instead of `scale`.
+    size: Option<Box<dyn Fn(Vec2) -> Vec2>>,
+    /// Wraps `FrameCount` for RetroArch shaders.
+    frame_count_mod: Option<u32>,
+    /// Keeps the previous output apart so the pass can read it while drawing
+    /// the next one.
+    persistent: bool,
+    target: Option<RenderTarget>,
+    previous: Option<RenderTarget>,
+}
+
+impl Pass {
+    pub fn new(
+        shader: Shader,
+        samplers: Vec<Sampler>,
+        scale: Option<(Scale, Scale)>,
+        filter: FilterMode,
+    ) -> Self {
+        Self {
+            shader,
+            samplers,
+            scale,
+            filter,
+            size: None,
+            frame_count_mod: None,
+            persistent: false,
+            target: None,
+            previous: None,
+        }
+    }
+
+    pub fn with_size(mut self, size: impl Fn(Vec2) -> Vec2 + 'static) -> Self {
+        self.size = Some(Box::new(size));
+        self
+    }
+
+    pub fn with_frame_count_mod(mut self, frame_count_mod: Option<u32>) -> Self {
+        self.frame_count_mod = frame_count_mod;
+        self
+    }
+
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+}
+
+/// Loads an image for use as a lookup texture.
+pub fn load_lut(path: &Path, filter: FilterMode) -> Result<Texture2D, String> {
+    let rgba = image::open(path)
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .into_rgba8();
+    let texture = Texture2D::from_rgba8(rgba.width() as u16, rgba.height() as u16, rgba.as_raw());
+    texture.set_filter(filter);
+    Ok(texture)
+}
+
+/// The last frames of a texture, most recent first, kept in render targets
+/// that are reused as new frames come in.
+#[derive(Default)]
+struct History {
+    frames: Vec<RenderTarget>,
+}
+
+impl History {
+    /// Keeps a copy of `texture`, dropping frames beyond `depth`.
+    fn push(&mut self, texture: &Texture2D, depth: usize) {
+        if depth == 0 {
+            self.frames.clear();
+            return;
+        }
+        self.frames.truncate(depth);
+        let mut oldest = if self.frames.len() == depth {
+            self.frames.pop()
+        } else {
+            None
+        };
+        let size = texture.size();
+        let target = sized_target(&mut oldest, size);
+        set_camera(&target_camera(&target));
+        draw_texture_ex(
+            texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
+        self.frames.insert(0, target);
+    }
+
+    /// The frame `n` frames back, or the oldest one while there are fewer.
+    fn get(&self, n: usize) -> Option<&Texture2D> {
+        self.frames
+            .get(n - 1)
+            .or(self.frames.last())
+            .map(|t| &t.texture)
+    }
+}
+
+/// Declarations a shader in pass `pass` needs to use `samplers`.
+pub fn layout(pass: usize, samplers: &[Sampler]) -> Layout {
+    Layout {
+        pass,
+        textures: samplers.iter().map(|s| s.name.clone()).collect(),
+        uniforms: samplers
+            .iter()
+            .flat_map(Sampler::size_uniforms)
+            .map(|name| UniformDesc::new(&name, UniformType::Float2))
+            .collect(),
+    }
+}
+
+pub struct Pipeline {
+    name: String,
+    passes: Vec<Pass>,
+    luts: Vec<Texture2D>,
+    /// Stands in for feedback before a pass has drawn anything.
+    black: Texture2D,
+    outputs: History,
+    inputs: History,
+    grade: Option<Grade>,
+}
+
+impl Pipeline {
+    /// Fails if there are no passes, since the last one draws the output.
+    pub fn new(name: String, passes: Vec<Pass>, luts: Vec<Texture2D>) -> Result<Self, String> {
+        if passes.is_empty() {
+            return Err(format!("{name}: no passes"));
+        }
+        Ok(Self {
+            name,
+            passes,
+            luts,
+            black: Texture2D::from_rgba8(1, 1, &[0, 0, 0, 255]),
+            outputs: History::default(),
+            inputs: History::default(),
+            grade: None,
+        })
+    }
+
+    pub fn builtin(effect: &Effect, inputs: usize) -> Self {
+        let passes = effect
+            .passes
+            .iter()
+            .enumerate()
+            .map(|(i, pass)| {
+                let samplers = samplers(i, inputs, &[], &[]);
+                let shader = Shader::builtin(pass.fragment, effect.params, &layout(i, &samplers));
+                Pass::new(shader, samplers, pass.scale, pass.filter)
+            })
+            .collect();
+
+        Self::new(effect.name.to_string(), passes, Vec::new())
+            .expect("built-in effects have passes")
+    }
+
+    /// Loads one pass per file. A pass shows its input unchanged until its
+    /// file compiles.
+    pub fn from_files(specs: &[PassSpec], inputs: usize) -> Self {
+        let passes = specs
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let samplers = samplers(i, inputs, &[], &[]);
+                let shader =
+                    Shader::from_file(&spec.path, effects::PASSTHROUGH, &layout(i, &samplers));
+                Pass::new(shader, samplers, spec.scale, spec.filter)
+            })
+            .collect();
+
+        let names: Vec<String> = specs.iter().map(|s| s.path.display().to_string()).collect();
+        Self::new(names.join(" > "), passes, Vec::new()).expect("there is a pass per file")
+    }
+
+    pub fn with_grade(mut self, grade: Option<Grade>) -> Self {
+        self.grade = grade;
+        self
+    }
+
+    /// Removes the grading pass, to move it to another pipeline.
+    pub fn take_grade(&mut self) -> Option<Grade> {
+        self.grade.take()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The first compile error of any pass.
+    pub fn error(&self) -> Option<&str> {
+        self.passes.iter().find_map(|pass| pass.shader.error())
+    }
+
+    /// The parameters of every pass, shared by passes that declare the same
+    /// name.
+    pub fn parameters(&self) -> Vec<Parameter> {
+        let shaders = self.passes.iter().map(|p| &p.shader);
+        let grade = self.grade.as_ref().map(Grade::shader);
+        let mut parameters: Vec<Parameter> = Vec::new();
+        for parameter in shaders.chain(grade).flat_map(Shader::parameters) {
+            if !parameters.iter().any(|p| p.name == parameter.name) {
+                parameters.push(parameter.clone());
+            }
+        }
+        parameters
+    }
+
+    pub fn set_parameter(&mut self, name: &str, value: f32) {
+        for pass in &mut self.passes {
+            pass.shader.set_parameter(name, value);
+        }
+        if let Some(grade) = &mut self.grade {
+            grade.shader_mut().set_parameter(name, value);
+        }
+    }
+
+    /// Reloads changed shader files.
+    pub fn poll(&mut self) {
+        for pass in &mut self.passes {
+            pass.shader.poll();
+        }
+    }
+
+    /// Runs every pass over `inputs` and draws the result into `viewport`,
+    /// the part of the window the image goes in, through `output`.
+    pub fn draw(
+        &mut self,
+        inputs: &[Texture2D],
+        uniforms: &Uniforms,
+        viewport: Rect,
+        output: &Output,
+    ) {
+        let mut inputs = inputs.to_vec();
+        let grade_after = match &mut self.grade {
+            Some(grade) if grade.position == Position::Before => {
+                inputs[0] = grade.render(&inputs[0], uniforms);
+                false
+            }
+            grade => grade.is_some(),
+        };
+        let last = self.passes.len() - 1;
+        let pixels = viewport::pixels(viewport);
+        let render = output.size().unwrap_or(pixels);
+        let mut source = inputs[0].clone();
+        let mut outputs: Vec<Texture2D> = Vec::new();
+
+        // Only keep as many frames as the shaders sample.
+        let depth = |history: fn(Binding) -> Option<usize>| {
+            self.passes
+                .iter()
+                .flat_map(|pass| {
+                    pass.samplers
+                        .iter()
+                        .filter(|s| pass.shader.uses(&s.name))
+                        .filter_map(|s| history(s.binding))
+                })
+                .max()
+                .unwrap_or(0)
+        };
+        let output_depth = depth(|b| match b {
+            Binding::PrevFrame(n) => Some(n),
+            _ => None,
+        });
+        let input_depth = depth(|b| match b {
+            Binding::PrevInput(n) => Some(n),
+            _ => None,
+        });
+
+        // What the passes drew last frame, before anything is overwritten.
+        let feedback: Vec<Texture2D> = self
+            .passes
+            .iter_mut()
+            .map(|pass| {
+                if pass.persistent {
+                    std::mem::swap(&mut pass.target, &mut pass.previous);
+                }
+                let last = if pass.persistent {
+                    &pass.previous
+                } else {
+                    &pass.target
+                };
+                last.as_ref()
+                    .map_or_else(|| self.black.clone(), |t| t.texture.clone())
+            })
+            .collect();
+
+        for (i, pass) in self.passes.iter_mut().enumerate() {
+            let fixed = output.size().is_some();
+            let scale = match pass.scale {
+                None if i == 0 && output.scale() > 1 => {
+                    let n = output.scale() as f32;
+                    Some((Scale::Source(n), Scale::Source(n)))
+                }
+                // Keeping or scaling the output needs it in a texture.
+                None if i != last
+                    || pass.size.is_some()
+                    || output_depth > 0
+                    || grade_after
+                    || fixed =>
+                {
+                    Some((Scale::Source(1.0), Scale::Source(1.0)))
+                }
+                scale => scale,
+            };
+            // Where the pass draws, and its size in pixels.
+            let (dest, size, mvp) = match scale {
+                Some((x, y)) => {
+                    let size = match &pass.size {
+                        Some(size) => size(render)
+                            .round()
+                            .max(Vec2::ONE)
+                            .min(Vec2::splat(MAX_SIZE)),
+                        None => vec2(
+                            x.size(source.size().x, pixels.x),
+                            y.size(source.size().y, pixels.y),
+                        ),
+                    };
+                    let target = sized_target(&mut pass.target, size);
+                    let camera = target_camera(&target);
+                    set_camera(&camera);
+                    (Rect::new(0.0, 0.0, size.x, size.y), size, camera.matrix())
+                }
+                None => {
+                    pass.target = None;
+                    pass.previous = None;
+                    set_default_camera();
+                    let (w, h) = (screen_width(), screen_height());
+                    let mvp = Mat4::orthographic_rh_gl(0.0, w, h, 0.0, -1.0, 1.0);
+                    (viewport, pixels, mvp)
+                }
+            };
+            clear_background(BLACK);
+
+            source.set_filter(pass.filter);
+            let info = PassInfo {
+                output: size,
+                input: source.size(),
+                frame_count_mod: pass.frame_count_mod,
+                mvp,
+            };
+            pass.shader.apply(uniforms, &info);
+            for sampler in &pass.samplers {
+                let texture = match sampler.binding {
+                    Binding::Input(n) => &inputs[n],
+                    Binding::Pass(n) => &outputs[n],
+                    Binding::Feedback(n) => &feedback[n],
+                    Binding::PrevFrame(n) => self.outputs.get(n).unwrap_or(&self.black),
+                    Binding::PrevInput(n) => self.inputs.get(n).unwrap_or(&inputs[0]),
+                    Binding::Lut(n) => &self.luts[n],
+                };
+                pass.shader.set_texture(&sampler.name, texture);
+                for name in sampler.size_uniforms() {
+                    pass.shader.set_uniform(&name, texture.size());
+                }
+            }
+            draw_texture_ex(
+                &source,
+                dest.x,
+                dest.y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(dest.size()),
+                    ..Default::default()
+                },
+            );
+            gl_use_default_material();
+
+            if let Some(target) = &pass.target {
+                source = target.texture.clone();
+                outputs.push(source.clone());
+            }
+        }
+
+        self.outputs.push(&source, output_depth);
+        self.inputs.push(&inputs[0], input_depth);
+
+        set_default_camera();
+        if self.passes[last].target.is_some() {
+            let source = match &mut self.grade {
+                Some(grade) if grade_after => grade.render(&source, uniforms),
+                _ => source,
+            };
+            output.present(&source, uniforms, viewport);
+        }
+    }
+}
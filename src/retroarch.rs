@@ -0,0 +1,215 @@
+//! RetroArch `.glslp` presets and the `.glsl` shaders they list.
+//!
+//! A RetroArch shader holds both stages in one file, selected with `VERTEX`
+//! and `FRAGMENT` defines. Its attributes are renamed to the ones macroquad
+//! feeds, which GL widens to the `vec4` RetroArch declares. Framebuffer
+//! formats, wrap modes and mipmaps in presets are ignored.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use macroquad::prelude::{FilterMode, Texture2D};
+
+use crate::effects;
+use crate::pipeline::{self, Pass, Pipeline, Scale};
+use crate::shader::{self, Shader};
+
+const VERTEX_PREAMBLE: &str = "#version 100
+#define VERTEX
+#define PARAMETER_UNIFORM
+#define VertexCoord position
+#define TexCoord texcoord
+#define COLOR color0
+#line 1
+";
+
+const FRAGMENT_PREAMBLE: &str = "#version 100
+#define FRAGMENT
+#define PARAMETER_UNIFORM
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+#line 1
+";
+
+/// Whether `source` is a RetroArch shader rather than a plain fragment shader.
+pub fn is_retroarch(source: &str) -> bool {
+    source.contains("defined(VERTEX)") || source.contains("#ifdef VERTEX")
+}
+
+/// Splits a RetroArch shader into GLSL 100 vertex and fragment shaders. Line
+/// numbers in compile errors match `source`.
+pub fn translate(source: &str) -> (String, String) {
+    let body = shader::strip_version(source);
+    (
+        format!("{VERTEX_PREAMBLE}{body}"),
+        format!("{FRAGMENT_PREAMBLE}{body}"),
+    )
+}
+
+/// Loads a `.glslp` preset as a pipeline over `inputs` inputs.
+pub fn load_preset(path: &Path, inputs: usize) -> Result<Pipeline, String> {
+    load(path, inputs).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn load(path: &Path, inputs: usize) -> Result<Pipeline, String> {
+    if path.extension().is_some_and(|e| e == "slangp") {
+        return Err("slang presets are not supported, use the .glslp version".to_string());
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let keys = parse(&text)?;
+    let preset = Preset {
+        keys,
+        dir: path.parent().unwrap_or(Path::new(".")),
+    };
+
+    let count: usize = preset.number("shaders")?.ok_or("missing 'shaders'")?;
+    if count == 0 {
+        return Err("'shaders' must be at least 1".to_string());
+    }
+    let lut_names = preset.list("textures");
+    let luts = lut_names
+        .iter()
+        .map(|name| preset.lut(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let overrides = preset
+        .list("parameters")
+        .into_iter()
+        .filter_map(|name| Some((name.clone(), preset.number(&name).ok()??)))
+        .collect::<Vec<(String, f32)>>();
+    let aliases: Vec<Option<String>> = (0..count)
+        .map(|i| preset.get(&format!("alias{i}")).map(str::to_string))
+        .collect();
+
+    let mut passes = Vec::new();
+    for i in 0..count {
+        let shader = preset
+            .get(&format!("shader{i}"))
+            .ok_or(format!("missing 'shader{i}'"))?;
+        let samplers = pipeline::samplers(i, inputs, &aliases, &lut_names);
+        let shader = Shader::with_overrides(
+            &preset.dir.join(shader),
+            effects::PASSTHROUGH,
+            &pipeline::layout(i, &samplers),
+            overrides.clone(),
+        );
+        let filter = match preset.flag(&format!("filter_linear{i}"))? {
+            Some(true) => FilterMode::Linear,
+            _ => FilterMode::Nearest,
+        };
+        let scale = match (preset.scale(i, "x")?, preset.scale(i, "y")?) {
+            (None, None) => None,
+            (x, y) => Some((
+                x.unwrap_or(Scale::Source(1.0)),
+                y.unwrap_or(Scale::Source(1.0)),
+            )),
+        };
+        let frame_count_mod = preset.number(&format!("frame_count_mod{i}"))?;
+        passes
+            .push(Pass::new(shader, samplers, scale, filter).with_frame_count_mod(frame_count_mod));
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    Pipeline::new(name.into_owned(), passes, luts)
+}
+
+/// Parses `key = value` lines, values optionally quoted.
+fn parse(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut keys = HashMap::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("#reference") {
+            return Err("#reference presets are not supported".to_string());
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(format!("expected 'key = value', got '{line}'"))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+            None => value.split('#').next().unwrap_or_default().trim(),
+        };
+        keys.insert(key.trim().to_string(), value.to_string());
+    }
+    Ok(keys)
+}
+
+struct Preset<'a> {
+    keys: HashMap<String, String>,
+    /// Paths in the preset are relative to it.
+    dir: &'a Path,
+}
+
+impl Preset<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.keys.get(key).map(String::as_str)
+    }
+
+    fn number<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        self.get(key)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| format!("{key}: '{v}' is not a number"))
+            })
+            .transpose()
+    }
+
+    fn flag(&self, key: &str) -> Result<Option<bool>, String> {
+        self.get(key)
+            .map(|v| match v {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(format!("{key}: '{v}' is not true or false")),
+            })
+            .transpose()
+    }
+
+    /// A `;` separated list.
+    fn list(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Scale of pass `i` along `axis`, from `scale_type_{axis}{i}` or
+    /// `scale_type{i}` and the matching factor.
+    fn scale(&self, i: usize, axis: &str) -> Result<Option<Scale>, String> {
+        let pick = |a: String, b: String| if self.get(&a).is_some() { a } else { b };
+        let ty_key = pick(format!("scale_type_{axis}{i}"), format!("scale_type{i}"));
+        let factor_key = pick(format!("scale_{axis}{i}"), format!("scale{i}"));
+
+        let Some(ty) = self.get(&ty_key) else {
+            return Ok(None);
+        };
+        Ok(Some(match ty {
+            "source" => Scale::Source(self.number(&factor_key)?.unwrap_or(1.0)),
+            "viewport" => Scale::Viewport(self.number(&factor_key)?.unwrap_or(1.0)),
+            "absolute" => Scale::Absolute(
+                self.number(&factor_key)?
+                    .ok_or(format!("{ty_key}: absolute needs {factor_key}"))?,
+            ),
+            _ => return Err(format!("{ty_key}: unknown scale type '{ty}'")),
+        }))
+    }
+
+    /// Loads lookup texture `name`, linear unless `{name}_linear` is false.
+    fn lut(&self, name: &str) -> Result<Texture2D, String> {
+        let path = self
+            .get(name)
+            .map(|p| self.dir.join(p))
+            .ok_or(format!("missing path for texture '{name}'"))?;
+        let filter = match self.flag(&format!("{name}_linear"))? {
+            Some(false) => FilterMode::Nearest,
+            _ => FilterMode::Linear,
+        };
+        pipeline::load_lut(&path, filter)
+    }
+}
@@ -0,0 +1,404 @@
+//! Effect shaders, either built in or loaded from disk with hot reload.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use macroquad::prelude::{
+    Material, MaterialParams, ShaderSource, Texture2D, UniformDesc, UniformType, Vec2, Vec4,
+    get_time, gl_use_material, load_material,
+};
+
+use crate::VERTEX_SHADER;
+use crate::effects::PASSTHROUGH;
+use crate::include::{self, Expanded};
+use crate::params::{self, Parameter};
+use crate::uniforms::{self, PassInfo, Uniforms};
+use crate::validate::{self, Stage};
+use crate::{isf, retroarch, shadertoy};
+
+/// Seconds between checks for changes to the shader file.
+const POLL_INTERVAL: f64 = 0.25;
+
+/// Samplers and uniforms a shader can use besides `Texture` and the
+/// standard uniforms.
+#[derive(Clone, Default)]
+pub struct Layout {
+    /// Index of the pass the shader runs in.
+    pub pass: usize,
+    pub textures: Vec<String>,
+    pub uniforms: Vec<UniformDesc>,
+}
+
+/// Value of a shader parameter, set as a uniform of the matching type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamValue {
+    Float(f32),
+    /// Also used for `bool` uniforms.
+    Int(i32),
+    Vec2(Vec2),
+    Vec4(Vec4),
+}
+
+impl ParamValue {
+    fn uniform_type(self) -> UniformType {
+        match self {
+            ParamValue::Float(_) => UniformType::Float1,
+            ParamValue::Int(_) => UniformType::Int1,
+            ParamValue::Vec2(_) => UniformType::Float2,
+            ParamValue::Vec4(_) => UniformType::Float4,
+        }
+    }
+
+    fn set(self, material: &Material, name: &str) {
+        match self {
+            ParamValue::Float(v) => material.set_uniform(name, v),
+            ParamValue::Int(v) => material.set_uniform(name, v),
+            ParamValue::Vec2(v) => material.set_uniform(name, v),
+            ParamValue::Vec4(v) => material.set_uniform(name, v),
+        }
+    }
+}
+
+/// Compiles a shader. `params` are uniforms on top of the standard ones from
+/// [`crate::uniforms`] and `layout`. Only the textures of `layout` that
+/// `fragment` mentions are declared, since each takes a texture unit.
+pub fn compile(
+    vertex: &str,
+    fragment: &str,
+    layout: &Layout,
+    params: &[(String, ParamValue)],
+) -> Result<Material, String> {
+    let mut uniforms = uniforms::descs();
+    uniforms.extend(layout.uniforms.iter().cloned());
+    uniforms.extend(
+        params
+            .iter()
+            .map(|(name, value)| UniformDesc::new(name, value.uniform_type())),
+    );
+
+    load_material(
+        ShaderSource::Glsl { vertex, fragment },
+        MaterialParams {
+            uniforms,
+            textures: declared(fragment, layout),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// What to compile for the contents of a shader file.
+pub struct Translated {
+    pub vertex: String,
+    pub fragment: String,
+    /// Uniforms an ISF header declares, with their defaults.
+    pub inputs: Vec<(String, ParamValue)>,
+}
+
+pub fn translate(source: &str, layout: &Layout) -> Result<Translated, String> {
+    let plain = |fragment: String| Translated {
+        vertex: VERTEX_SHADER.to_string(),
+        fragment,
+        inputs: Vec::new(),
+    };
+    Ok(if retroarch::is_retroarch(source) {
+        let (vertex, fragment) = retroarch::translate(source);
+        Translated {
+            vertex,
+            fragment,
+            inputs: Vec::new(),
+        }
+    } else if isf::is_isf(source) {
+        let (fragment, inputs) = isf::translate(source, layout)?;
+        Translated {
+            inputs,
+            ..plain(fragment)
+        }
+    } else if shadertoy::is_shadertoy(source) {
+        plain(shadertoy::wrap(source, &layout.textures))
+    } else {
+        plain(source.to_string())
+    })
+}
+
+/// Describes a failed compile with the positions in `source` that the
+/// offline validator finds, or the driver message if it finds nothing.
+fn compile_error(source: &Expanded, vertex: &str, fragment: &str, error: String) -> String {
+    let diagnostics: Vec<String> = validate::check(Stage::Vertex, vertex)
+        .into_iter()
+        .chain(validate::check(Stage::Fragment, fragment))
+        .map(|d| d.at(source))
+        .collect();
+    if diagnostics.is_empty() {
+        format!("{}: {error}", source.path().display())
+    } else {
+        diagnostics.join("\n")
+    }
+}
+
+/// Whether `name` appears in `source` as a whole identifier.
+pub fn mentions(source: &str, name: &str) -> bool {
+    find_ident(source, name).is_some()
+}
+
+/// Byte offset of the first whole identifier `name` in `source`.
+pub fn find_ident(source: &str, name: &str) -> Option<usize> {
+    let ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    source
+        .match_indices(name)
+        .map(|(i, _)| i)
+        .find(|&i| !source[..i].ends_with(ident) && !source[i + name.len()..].starts_with(ident))
+}
+
+/// `source` with its `#version` line blanked, so a preamble can declare the
+/// version while line numbers stay the same.
+pub fn strip_version(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with("#version") {
+                "\n".to_string()
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect()
+}
+
+/// The textures of `layout` that [`compile`] declares for `fragment`.
+fn declared(fragment: &str, layout: &Layout) -> Vec<String> {
+    layout
+        .textures
+        .iter()
+        .filter(|name| mentions(fragment, name))
+        .cloned()
+        .collect()
+}
+
+/// The effect material. When loaded from a file it is recompiled whenever the
+/// file is saved, keeping the last good version if compilation fails.
+pub struct Shader {
+    path: Option<PathBuf>,
+    /// The file and the files it includes, watched for changes.
+    files: Vec<PathBuf>,
+    layout: Layout,
+    params: Vec<(String, ParamValue)>,
+    /// The `#pragma parameter`s, which are also in `params`.
+    parameters: Vec<Parameter>,
+    /// Values that replace the defaults of `parameters`.
+    overrides: Vec<(String, f32)>,
+    material: Material,
+    /// The textures `material` declares.
+    textures: Vec<String>,
+    /// When each of `files` was last changed.
+    modified: Vec<Option<SystemTime>>,
+    last_poll: f64,
+    error: Option<String>,
+}
+
+impl Shader {
+    /// Compiles `fragment`, with `overrides` replacing the defaults of the
+    /// parameters it declares. It can include snippets from the library.
+    pub fn builtin(fragment: &str, overrides: &[(&str, f32)], layout: &Layout) -> Self {
+        let overrides: Vec<(String, f32)> = overrides
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value))
+            .collect();
+        let result = include::expand(fragment, None).and_then(|source| {
+            let fragment = &source.source;
+            let parameters = parameters(fragment, &overrides);
+            let params = with_parameters(Vec::new(), &parameters);
+            let material = compile(VERTEX_SHADER, fragment, layout, &params)
+                .map_err(|e| compile_error(&source, VERTEX_SHADER, fragment, e))?;
+            Ok((material, declared(fragment, layout), params, parameters))
+        });
+        let (material, textures, params, parameters, error) = match result {
+            Ok((material, textures, params, parameters)) => {
+                (material, textures, params, parameters, None)
+            }
+            // Shown over the unchanged input rather than taking the app down.
+            Err(error) => {
+                let material = compile(VERTEX_SHADER, PASSTHROUGH, layout, &[])
+                    .expect("the passthrough shader compiles");
+                let textures = declared(PASSTHROUGH, layout);
+                (material, textures, Vec::new(), Vec::new(), Some(error))
+            }
+        };
+
+        Self {
+            path: None,
+            files: Vec::new(),
+            layout: layout.clone(),
+            material,
+            textures,
+            params,
+            parameters,
+            overrides,
+            modified: Vec::new(),
+            last_poll: 0.0,
+            error,
+        }
+    }
+
+    /// Loads `path`, using `fallback` until the file compiles.
+    pub fn from_file(path: &Path, fallback: &str, layout: &Layout) -> Self {
+        Self::with_overrides(path, fallback, layout, Vec::new())
+    }
+
+    /// Like [`Shader::from_file`], with values replacing the defaults of the
+    /// parameters the file declares.
+    pub fn with_overrides(
+        path: &Path,
+        fallback: &str,
+        layout: &Layout,
+        overrides: Vec<(String, f32)>,
+    ) -> Self {
+        let mut shader = Self::builtin(fallback, &[], layout);
+        shader.path = Some(path.to_path_buf());
+        shader.files = vec![path.to_path_buf()];
+        shader.overrides = overrides;
+        shader.reload();
+        shader
+    }
+
+    /// Makes the material current and sets its uniforms.
+    pub fn apply(&self, uniforms: &Uniforms, pass: &PassInfo) {
+        gl_use_material(&self.material);
+        uniforms.apply(&self.material, pass);
+        for (name, value) in &self.params {
+            value.set(&self.material, name);
+        }
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Changes the value of a `#pragma parameter`, kept across reloads.
+    pub fn set_parameter(&mut self, name: &str, value: f32) {
+        let Some(parameter) = self.parameters.iter_mut().find(|p| p.name == name) else {
+            return;
+        };
+        parameter.value = value;
+        for (n, v) in &mut self.params {
+            if n == name {
+                *v = ParamValue::Float(value);
+            }
+        }
+        self.overrides.retain(|(n, _)| n != name);
+        self.overrides.push((name.to_string(), value));
+    }
+
+    /// Whether the shader samples texture `name`.
+    pub fn uses(&self, name: &str) -> bool {
+        self.textures.iter().any(|t| t == name)
+    }
+
+    /// Binds `texture` if the shader uses `name`.
+    pub fn set_texture(&self, name: &str, texture: &Texture2D) {
+        if self.uses(name) {
+            self.material.set_texture(name, texture.clone());
+        }
+    }
+
+    /// Sets one of the `vec2` uniforms of the layout.
+    pub fn set_uniform(&self, name: &str, value: Vec2) {
+        self.material.set_uniform(name, value);
+    }
+
+    /// The compile error of the file on disk, if its last version failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Recompiles the file if it or a file it includes changed since the
+    /// last call.
+    pub fn poll(&mut self) {
+        if self.path.is_none() || get_time() - self.last_poll < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = get_time();
+
+        if modified(&self.files) != self.modified {
+            self.reload();
+        }
+    }
+
+    fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.modified = modified(&self.files);
+
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| format!("{}: {e}", path.display()))
+            .and_then(|source| include::expand(&source, Some(path)))
+            .and_then(|source| {
+                let Translated {
+                    vertex,
+                    fragment,
+                    inputs,
+                } = translate(&source.source, &self.layout)?;
+                let parameters = parameters(&source.source, &self.overrides);
+                let params = with_parameters(inputs, &parameters);
+
+                let material = compile(&vertex, &fragment, &self.layout, &params)
+                    .map_err(|e| compile_error(&source, &vertex, &fragment, e))?;
+                Ok((
+                    material,
+                    declared(&fragment, &self.layout),
+                    params,
+                    parameters,
+                    source.files(),
+                ))
+            });
+        match result {
+            Ok((material, textures, params, parameters, files)) => {
+                info!("loaded {}", path.display());
+                self.modified = modified(&files);
+                self.files = files;
+                self.material = material;
+                self.textures = textures;
+                self.params = params;
+                self.parameters = parameters;
+                self.error = None;
+            }
+            Err(e) => {
+                info!("failed to compile {}: {e}", path.display());
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// When each of `files` was last changed.
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// The parameters `source` declares, with their values from `overrides`.
+fn parameters(source: &str, overrides: &[(String, f32)]) -> Vec<Parameter> {
+    let mut parameters = params::parse(source);
+    for parameter in &mut parameters {
+        if let Some(&(_, value)) = overrides.iter().find(|(n, _)| *n == parameter.name) {
+            parameter.value = value;
+        }
+    }
+    parameters
+}
+
+/// Adds the `parameters` that are not in `params` yet.
+fn with_parameters(
+    mut params: Vec<(String, ParamValue)>,
+    parameters: &[Parameter],
+) -> Vec<(String, ParamValue)> {
+    for parameter in parameters {
+        if !params.iter().any(|(name, _)| *name == parameter.name) {
+            params.push((parameter.name.clone(), ParamValue::Float(parameter.value)));
+        }
+    }
+    params
+}
@@ -0,0 +1,17 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+// The blurred highlights from the previous passes.
+uniform sampler2D Texture;
+uniform sampler2D Original;
+#pragma parameter Strength "Strength" 1.0 0.0 3.0 0.1
+uniform float Strength;
+
+void main() {
+    vec3 base = texture2D(Original, uv).rgb;
+    vec3 glow = texture2D(Texture, uv).rgb;
+    gl_FragColor = vec4((base + glow * Strength) * color.rgb, 1.0);
+}
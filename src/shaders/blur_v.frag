@@ -0,0 +1,18 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+// Blur radius as a fraction of the image height.
+#pragma parameter Radius "Blur radius" 0.02 0.0 0.1 0.005
+uniform float Radius;
+
+#include "blur.glsl"
+
+void main() {
+    // Sigma of about a third of the radius.
+    vec3 sum = GaussianBlur9(Texture, uv, vec2(0.0, Radius / 4.0));
+    gl_FragColor = vec4(sum, 1.0);
+}
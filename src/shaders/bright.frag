@@ -0,0 +1,17 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+// Luma below which nothing glows.
+#pragma parameter Threshold "Threshold" 0.6 0.0 1.0 0.05
+uniform float Threshold;
+
+void main() {
+    vec3 rgb = texture2D(Texture, uv).rgb;
+    float luma = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+    float glow = max(luma - Threshold, 0.0) / max(1.0 - Threshold, 0.001);
+    gl_FragColor = vec4(rgb * glow, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+#pragma parameter Offset "Offset" 0.02 0.0 0.1 0.005
+uniform float Offset;
+
+void main() {
+    // Push red out and blue in, more towards the edges like a cheap lens.
+    vec2 dir = (uv - 0.5) * Offset;
+    float r = texture2D(Texture, uv + dir).r;
+    float g = texture2D(Texture, uv).g;
+    float b = texture2D(Texture, uv - dir).b;
+    gl_FragColor = vec4(vec3(r, g, b) * color.rgb, 1.0);
+}
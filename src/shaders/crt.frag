@@ -0,0 +1,55 @@
+#version 100
+precision lowp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+// The previous output, for phosphors that fade out slowly.
+uniform sampler2D PrevFrame;
+uniform float iTime;
+uniform vec2 iInputResolution;
+
+#pragma parameter CURVATURE_X "Curvature divisor X" 6.0 2.0 20.0 0.5
+#pragma parameter CURVATURE_Y "Curvature divisor Y" 4.0 2.0 20.0 0.5
+#pragma parameter VIGNETTE "Vignette exponent" 0.3 0.0 1.0 0.05
+#pragma parameter SCANLINE "Scanline strength" 0.05 0.0 0.5 0.01
+#pragma parameter GRILLE "Grille strength" 0.15 0.0 0.5 0.01
+#pragma parameter GRILLE_COUNT "Grille stripes per column" 1.0 0.25 2.0 0.25
+#pragma parameter BRIGHTNESS "Brightness" 1.2 0.5 2.0 0.05
+#pragma parameter PHOSPHOR "Phosphor persistence" 0.0 0.0 0.9 0.05
+uniform float CURVATURE_X;
+uniform float CURVATURE_Y;
+uniform float VIGNETTE;
+uniform float SCANLINE;
+uniform float GRILLE;
+uniform float GRILLE_COUNT;
+uniform float BRIGHTNESS;
+uniform float PHOSPHOR;
+
+#include "curvature.glsl"
+
+void DrawScanline( inout vec3 color, vec2 uv )
+{
+    // One scanline per two input rows, GRILLE_COUNT grille stripes per column.
+    float lines = iInputResolution.y * 0.5;
+    // Slow roll, wrapped so it stays precise as iTime grows.
+    float roll = fract( 0.004 * iTime * lines );
+    float scanline 	= clamp( 1.0 - SCANLINE + SCANLINE * cos( 3.14 * ( uv.y * lines + 2.0 * roll ) ), 0.0, 1.0 );
+    float grille 	= 1.0 - GRILLE + GRILLE * clamp( 1.5 * cos( 3.14 * uv.x * iInputResolution.x * GRILLE_COUNT ), 0.0, 1.0 );
+    color *= scanline * grille * BRIGHTNESS;
+}
+
+void main() {
+    vec2 crtUV = CRTCurveUV(uv, vec2(CURVATURE_X, CURVATURE_Y));
+    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
+    if (crtUV.x < 0.0 || crtUV.x > 1.0 || crtUV.y < 0.0 || crtUV.y > 1.0)
+    {
+        res = vec3(0.0, 0.0, 0.0);
+    }
+    DrawVignette(res, crtUV, VIGNETTE);
+    DrawScanline(res, uv);
+    res = max(res, texture2D(PrevFrame, uv).rgb * PHOSPHOR);
+    gl_FragColor = vec4(res, 1.0);
+
+}
@@ -0,0 +1,34 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D PrevInput1;
+uniform sampler2D PrevInput2;
+uniform sampler2D PrevInput3;
+// Difference from the current frame above which an old frame is ignored, so
+// moving things do not smear.
+#pragma parameter Tolerance "Tolerance" 0.1 0.01 0.5 0.01
+uniform float Tolerance;
+
+vec3 sum;
+float total;
+
+void add(vec3 now, sampler2D frame) {
+    vec3 rgb = texture2D(frame, uv).rgb;
+    float weight = 1.0 - smoothstep(Tolerance * 0.5, Tolerance, distance(rgb, now));
+    sum += rgb * weight;
+    total += weight;
+}
+
+void main() {
+    vec3 now = texture2D(Texture, uv).rgb;
+    sum = now;
+    total = 1.0;
+    add(now, PrevInput1);
+    add(now, PrevInput2);
+    add(now, PrevInput3);
+    gl_FragColor = vec4(sum / total * color.rgb, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec3 iResolution;
+
+// Averages the input under each output pixel with 4x4 bilinear taps, which
+// covers up to 8x8 input pixels without aliasing.
+void main() {
+    vec2 footprint = 1.0 / iResolution.xy;
+    vec3 sum = vec3(0.0);
+    for (int y = 0; y < 4; y++) {
+        for (int x = 0; x < 4; x++) {
+            vec2 offset = (vec2(float(x), float(y)) + 0.5) / 4.0 - 0.5;
+            sum += texture2D(Texture, uv + offset * footprint).rgb;
+        }
+    }
+    gl_FragColor = vec4(sum / 16.0, 1.0);
+}
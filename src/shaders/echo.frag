@@ -0,0 +1,17 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D PrevFrame;
+// Share of the previous output kept every frame.
+#pragma parameter Decay "Decay" 0.85 0.0 0.98 0.01
+uniform float Decay;
+
+void main() {
+    vec3 now = texture2D(Texture, uv).rgb * color.rgb;
+    vec3 before = texture2D(PrevFrame, uv).rgb;
+    gl_FragColor = vec4(mix(now, before, Decay), 1.0);
+}
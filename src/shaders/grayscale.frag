@@ -0,0 +1,15 @@
+#version 100
+precision lowp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+
+#include "color.glsl"
+
+void main() {
+    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
+    float luma = Luma(res);
+    gl_FragColor = vec4(vec3(luma), 1.0);
+}
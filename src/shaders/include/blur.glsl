@@ -0,0 +1,31 @@
+// Separable gaussian blur kernels. Run one pass along x and one along y.
+#ifndef BLUR_GLSL
+#define BLUR_GLSL
+
+// 5 taps `offset` apart, sigma of one offset.
+vec3 GaussianBlur5(sampler2D tex, vec2 uv, vec2 offset)
+{
+    vec3 sum = texture2D(tex, uv).rgb * 0.4026;
+    sum += texture2D(tex, uv + offset).rgb * 0.2442;
+    sum += texture2D(tex, uv - offset).rgb * 0.2442;
+    sum += texture2D(tex, uv + 2.0 * offset).rgb * 0.0545;
+    sum += texture2D(tex, uv - 2.0 * offset).rgb * 0.0545;
+    return sum;
+}
+
+// 9 taps `offset` apart, sigma of about 1.33 offsets.
+vec3 GaussianBlur9(sampler2D tex, vec2 uv, vec2 offset)
+{
+    vec3 sum = texture2D(tex, uv).rgb * 0.2270;
+    sum += texture2D(tex, uv + offset).rgb * 0.1945;
+    sum += texture2D(tex, uv - offset).rgb * 0.1945;
+    sum += texture2D(tex, uv + 2.0 * offset).rgb * 0.1216;
+    sum += texture2D(tex, uv - 2.0 * offset).rgb * 0.1216;
+    sum += texture2D(tex, uv + 3.0 * offset).rgb * 0.0540;
+    sum += texture2D(tex, uv - 3.0 * offset).rgb * 0.0540;
+    sum += texture2D(tex, uv + 4.0 * offset).rgb * 0.0162;
+    sum += texture2D(tex, uv - 4.0 * offset).rgb * 0.0162;
+    return sum;
+}
+
+#endif
@@ -0,0 +1,58 @@
+// Color space conversions. Colors are RGB in [0, 1].
+#ifndef COLOR_GLSL
+#define COLOR_GLSL
+
+// Rec. 709 luma, for linear or gamma encoded RGB alike.
+float Luma(vec3 rgb)
+{
+    return dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Hue, saturation and value, all in [0, 1].
+// http://lolengine.net/blog/2013/07/27/rgb-to-hsv-in-glsl
+vec3 RgbToHsv(vec3 c)
+{
+    vec4 K = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    vec4 p = mix(vec4(c.bg, K.wz), vec4(c.gb, K.xy), step(c.b, c.g));
+    vec4 q = mix(vec4(p.xyw, c.r), vec4(c.r, p.yzx), step(p.x, c.r));
+    float d = q.x - min(q.w, q.y);
+    float e = 1.0e-10;
+    return vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+vec3 HsvToRgb(vec3 c)
+{
+    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+}
+
+// Full range BT.601 as in JPEG, with Cb and Cr centered on 0.5.
+vec3 RgbToYCbCr(vec3 rgb)
+{
+    float y = dot(rgb, vec3(0.299, 0.587, 0.114));
+    return vec3(y, (rgb.b - y) * 0.564 + 0.5, (rgb.r - y) * 0.713 + 0.5);
+}
+
+vec3 YCbCrToRgb(vec3 ycc)
+{
+    float cb = ycc.y - 0.5;
+    float cr = ycc.z - 0.5;
+    return vec3(ycc.x + 1.402 * cr, ycc.x - 0.344136 * cb - 0.714136 * cr, ycc.x + 1.772 * cb);
+}
+
+vec3 SrgbToLinear(vec3 c)
+{
+    vec3 low = c / 12.92;
+    vec3 high = pow((c + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, step(c, vec3(0.04045)));
+}
+
+vec3 LinearToSrgb(vec3 c)
+{
+    vec3 low = c * 12.92;
+    vec3 high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, step(c, vec3(0.0031308)));
+}
+
+#endif
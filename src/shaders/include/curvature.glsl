@@ -0,0 +1,25 @@
+// The curved face and dark corners of a CRT.
+#ifndef CURVATURE_GLSL
+#define CURVATURE_GLSL
+
+// Bends uv outwards from the center, more for smaller divisors. Parts of
+// the result outside [0, 1] are off the screen.
+// https://www.shadertoy.com/view/XtlSD7
+vec2 CRTCurveUV(vec2 uv, vec2 divisor)
+{
+    uv = uv * 2.0 - 1.0;
+    vec2 offset = abs( uv.yx ) / divisor;
+    uv = uv + uv * offset * offset;
+    uv = uv * 0.5 + 0.5;
+    return uv;
+}
+
+// Darkens the corners, less for smaller exponents.
+void DrawVignette( inout vec3 color, vec2 uv, float exponent )
+{
+    float vignette = uv.x * uv.y * ( 1.0 - uv.x ) * ( 1.0 - uv.y );
+    vignette = clamp( pow( 16.0 * vignette, exponent ), 0.0, 1.0 );
+    color *= vignette;
+}
+
+#endif
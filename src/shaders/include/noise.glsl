@@ -0,0 +1,48 @@
+// Hashes and noise that give the same values on every GPU, without sin().
+#ifndef NOISE_GLSL
+#define NOISE_GLSL
+
+// Hash without Sine, https://www.shadertoy.com/view/4djSRW
+// Pseudo random value in [0, 1) for a 2D point.
+float Hash12(vec2 p)
+{
+    vec3 p3 = fract(vec3(p.xyx) * 0.1031);
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.x + p3.y) * p3.z);
+}
+
+// Two pseudo random values in [0, 1) for a 2D point.
+vec2 Hash22(vec2 p)
+{
+    vec3 p3 = fract(vec3(p.xyx) * vec3(0.1031, 0.1030, 0.0973));
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.xx + p3.yz) * p3.zy);
+}
+
+// Smooth noise in [0, 1) with features about one unit apart.
+float ValueNoise(vec2 p)
+{
+    vec2 i = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+    float a = Hash12(i);
+    float b = Hash12(i + vec2(1.0, 0.0));
+    float c = Hash12(i + vec2(0.0, 1.0));
+    float d = Hash12(i + vec2(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Five octaves of value noise, in [0, 1).
+float Fbm(vec2 p)
+{
+    float sum = 0.0;
+    float amplitude = 0.5;
+    for (int i = 0; i < 5; i++) {
+        sum += amplitude * ValueNoise(p);
+        p *= 2.0;
+        amplitude *= 0.5;
+    }
+    return sum / 0.96875;
+}
+
+#endif
@@ -0,0 +1,28 @@
+#version 100
+// Texel centers in a strip hundreds of texels wide need more than mediump.
+precision highp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+// The blue slices of the table side by side, red across and green down.
+uniform sampler2D Lut;
+uniform vec2 LutSize;
+#pragma parameter LutStrength "LUT strength" 1.0 0.0 1.0 0.05
+uniform float LutStrength;
+
+vec3 lookup(float slice, vec2 rg, float n) {
+    // Texel centers, so linear filtering stays inside the slice.
+    vec2 pos = vec2(slice * n, 0.0) + rg * (n - 1.0) + 0.5;
+    return texture2D(Lut, pos / LutSize).rgb;
+}
+
+void main() {
+    vec3 rgb = clamp(texture2D(Texture, uv).rgb, 0.0, 1.0);
+    float n = LutSize.y;
+    float b = rgb.b * (n - 1.0);
+    float b0 = min(floor(b), n - 2.0);
+    vec3 graded = mix(lookup(b0, rgb.rg, n), lookup(b0 + 1.0, rgb.rg, n), b - b0);
+    gl_FragColor = vec4(mix(rgb, graded, LutStrength) * color.rgb, 1.0);
+}
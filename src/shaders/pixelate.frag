@@ -0,0 +1,16 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+// Number of blocks across the image, rows assume 4:3 like most webcams.
+#pragma parameter Cells "Cells" 80.0 8.0 320.0 4.0
+uniform float Cells;
+
+void main() {
+    vec2 cells = vec2(Cells, Cells * 0.75);
+    vec2 block = (floor(uv * cells) + 0.5) / cells;
+    gl_FragColor = vec4(texture2D(Texture, block).rgb * color.rgb, 1.0);
+}
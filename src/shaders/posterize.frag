@@ -0,0 +1,15 @@
+#version 100
+precision lowp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+#pragma parameter Levels "Levels" 4.0 2.0 16.0 1.0
+uniform float Levels;
+
+void main() {
+    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
+    res = floor(res * Levels + 0.5) / Levels;
+    gl_FragColor = vec4(res, 1.0);
+}
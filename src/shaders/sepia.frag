@@ -0,0 +1,19 @@
+#version 100
+precision lowp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+#pragma parameter Amount "Amount" 1.0 0.0 1.0 0.05
+uniform float Amount;
+
+void main() {
+    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
+    vec3 sepia = vec3(
+        dot(res, vec3(0.393, 0.769, 0.189)),
+        dot(res, vec3(0.349, 0.686, 0.168)),
+        dot(res, vec3(0.272, 0.534, 0.131))
+    );
+    gl_FragColor = vec4(mix(res, min(sepia, 1.0), Amount), 1.0);
+}
@@ -0,0 +1,22 @@
+#version 100
+precision highp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 TextureSize;
+uniform vec2 OutputSize;
+
+// Scales up by the largest whole multiple with nearest filtering and the
+// rest with linear filtering, so pixels stay sharp without uneven sizes.
+// Needs linear filtering on Texture.
+void main() {
+    vec2 scale = max(floor(OutputSize / TextureSize), 1.0);
+    vec2 texel = uv * TextureSize;
+    // Only the edge of each scaled up pixel is blended with its neighbour.
+    vec2 edge = 0.5 - 0.5 / scale;
+    vec2 center = fract(texel) - 0.5;
+    vec2 f = (center - clamp(center, -edge, edge)) * scale + 0.5;
+    gl_FragColor = texture2D(Texture, (floor(texel) + f) / TextureSize) * color;
+}
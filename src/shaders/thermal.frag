@@ -0,0 +1,32 @@
+#version 100
+precision lowp float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+
+#include "color.glsl"
+
+// Black, blue, magenta, orange, yellow, white false color ramp.
+vec3 Thermal(float t)
+{
+    vec3 c0 = vec3(0.0, 0.0, 0.0);
+    vec3 c1 = vec3(0.1, 0.0, 0.6);
+    vec3 c2 = vec3(0.8, 0.0, 0.6);
+    vec3 c3 = vec3(1.0, 0.5, 0.0);
+    vec3 c4 = vec3(1.0, 1.0, 0.2);
+    vec3 c5 = vec3(1.0, 1.0, 1.0);
+    t = clamp(t, 0.0, 1.0) * 5.0;
+    if (t < 1.0) return mix(c0, c1, t);
+    if (t < 2.0) return mix(c1, c2, t - 1.0);
+    if (t < 3.0) return mix(c2, c3, t - 2.0);
+    if (t < 4.0) return mix(c3, c4, t - 3.0);
+    return mix(c4, c5, t - 4.0);
+}
+
+void main() {
+    vec3 res = texture2D(Texture, uv).rgb * color.rgb;
+    float luma = Luma(res);
+    gl_FragColor = vec4(Thermal(luma), 1.0);
+}
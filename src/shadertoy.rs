@@ -0,0 +1,75 @@
+//! Runs Shadertoy image shaders, which define `mainImage` instead of `main`
+//! and are written against WebGL 2.
+
+use crate::shader;
+
+/// Declarations in front of the user source. Shadertoy textures have their
+/// origin at the bottom left and ours at the top left, so every lookup
+/// through `texture` is flipped to match.
+const PREAMBLE: &str = r#"#version 100
+#extension GL_OES_standard_derivatives : enable
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec3 iResolution;
+uniform vec2 iInputResolution;
+uniform vec4 iDate;
+uniform vec4 iMouse;
+
+vec3 iChannelResolution[4];
+float iChannelTime[4];
+const float iSampleRate = 44100.0;
+#define iFrameRate (1.0 / iTimeDelta)
+
+vec4 ShadertoyTexture(sampler2D s, vec2 p) { return texture2D(s, vec2(p.x, 1.0 - p.y)); }
+vec4 ShadertoyTexture(sampler2D s, vec2 p, float bias) { return texture2D(s, vec2(p.x, 1.0 - p.y), bias); }
+#define texture ShadertoyTexture
+#define textureLod(s, p, lod) ShadertoyTexture(s, p)
+"#;
+
+const MAIN: &str = r#"
+void main() {
+    for (int i = 0; i < 4; i++) {
+        iChannelResolution[i] = vec3(iInputResolution, 1.0);
+        iChannelTime[i] = iTime;
+    }
+    vec4 fragColor = vec4(0.0);
+    mainImage(fragColor, vec2(uv.x, 1.0 - uv.y) * iResolution.xy);
+    gl_FragColor = vec4(fragColor.rgb, 1.0);
+}
+"#;
+
+/// Whether `source` is a Shadertoy shader rather than a plain fragment shader.
+pub fn is_shadertoy(source: &str) -> bool {
+    shader::mentions(source, "mainImage") && !shader::mentions(source, "main")
+}
+
+/// Turns a Shadertoy shader into a GLSL 100 fragment shader. `iChannel0` is
+/// the pass input and `iChannelN` the extra input `TextureN` if it is in
+/// `textures`, the pass input otherwise. Line numbers in compile errors match
+/// `source`.
+pub fn wrap(source: &str, textures: &[String]) -> String {
+    let mut out = PREAMBLE.to_string();
+    for i in 1..4 {
+        let name = format!("Texture{i}");
+        if textures.contains(&name) {
+            out += &format!("uniform sampler2D {name};\n#define iChannel{i} {name}\n");
+        } else {
+            out += &format!("#define iChannel{i} Texture\n");
+        }
+    }
+    out += "#define iChannel0 Texture\n#line 1\n";
+    out += source;
+    out += MAIN;
+    out
+}
@@ -0,0 +1,373 @@
+use std::time::Instant;
+
+use nokhwa::{Camera, NokhwaError, pixel_format::*, query, utils::*};
+
+use super::{Frame, FrameSource, PixelFormat, SourceError};
+
+/// What to pick when the requested format is not offered exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Fallback {
+    /// Nearest resolution, then nearest frame rate.
+    #[default]
+    Closest,
+    HighestFps,
+    HighestResolution,
+}
+
+/// Capture format wanted from the camera. Unset fields match anything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FormatRequest {
+    pub resolution: Option<(u32, u32)>,
+    pub fps: Option<u32>,
+    pub fourcc: Option<FrameFormat>,
+    pub fallback: Fallback,
+}
+
+impl FormatRequest {
+    fn is_any(&self) -> bool {
+        self.resolution.is_none() && self.fps.is_none() && self.fourcc.is_none()
+    }
+
+    fn matches(&self, f: &CameraFormat) -> bool {
+        self.resolution
+            .is_none_or(|(w, h)| f.width() == w && f.height() == h)
+            && self.fps.is_none_or(|fps| f.frame_rate() == fps)
+            && self.fourcc.is_none_or(|fourcc| f.format() == fourcc)
+    }
+
+    /// Picks the format to open from what the camera offers.
+    fn negotiate(&self, formats: &[CameraFormat]) -> Option<CameraFormat> {
+        if let Some(exact) = formats.iter().find(|f| self.matches(f)) {
+            return Some(*exact);
+        }
+
+        // Keep the pixel format if it exists at all, it matters most for latency.
+        let candidates: Vec<CameraFormat> = match self.fourcc {
+            Some(fourcc) if formats.iter().any(|f| f.format() == fourcc) => formats
+                .iter()
+                .filter(|f| f.format() == fourcc)
+                .copied()
+                .collect(),
+            _ => formats.to_vec(),
+        };
+
+        let area = |f: &CameraFormat| f.width() * f.height();
+        match self.fallback {
+            Fallback::Closest => {
+                let (w, h) = self.resolution.unwrap_or((0, 0));
+                let fps = self.fps.unwrap_or(0);
+                candidates.into_iter().min_by_key(|f| {
+                    let dist = if self.resolution.is_some() {
+                        f.width().abs_diff(w).pow(2) + f.height().abs_diff(h).pow(2)
+                    } else {
+                        0
+                    };
+                    let fps_dist = if self.fps.is_some() {
+                        f.frame_rate().abs_diff(fps)
+                    } else {
+                        0
+                    };
+                    (dist, fps_dist)
+                })
+            }
+            Fallback::HighestFps => candidates
+                .into_iter()
+                .max_by_key(|f| (f.frame_rate(), area(f))),
+            Fallback::HighestResolution => candidates
+                .into_iter()
+                .max_by_key(|f| (area(f), f.frame_rate())),
+        }
+    }
+}
+
+impl std::fmt::Display for FormatRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.resolution {
+            Some((w, h)) => write!(f, "{w}x{h}")?,
+            None => write!(f, "any")?,
+        }
+        if let Some(fps) = self.fps {
+            write!(f, "@{fps}")?;
+        }
+        if let Some(fourcc) = self.fourcc {
+            write!(f, " {fourcc}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Webcam input through nokhwa.
+pub struct CameraSource {
+    cam: Camera,
+    /// Pass YUYV and NV12 through undecoded for conversion on the GPU.
+    raw_yuv: bool,
+    start: Instant,
+}
+
+impl CameraSource {
+    pub fn open(
+        index: CameraIndex,
+        request: &FormatRequest,
+        raw_yuv: bool,
+    ) -> Result<Self, SourceError> {
+        let open_err = |e: NokhwaError| SourceError::Open(e.to_string());
+
+        let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::None);
+        let mut cam = Camera::new(index, format).map_err(open_err)?;
+
+        if !request.is_any() {
+            let formats = cam.compatible_camera_formats().map_err(open_err)?;
+            match request.negotiate(&formats) {
+                Some(chosen) => {
+                    let exact = RequestedFormatType::Exact(chosen);
+                    cam.set_camera_requset(RequestedFormat::new::<RgbAFormat>(exact))
+                        .map_err(open_err)?;
+                }
+                None => info!("camera offers no formats, using the driver default"),
+            }
+        }
+        cam.open_stream().map_err(open_err)?;
+
+        let fmt = cam.camera_format();
+        info!(
+            "camera {}: requested {request}, negotiated {}x{}@{} {}",
+            cam.info().human_name(),
+            fmt.width(),
+            fmt.height(),
+            fmt.frame_rate(),
+            fmt.format()
+        );
+
+        Ok(Self {
+            cam,
+            raw_yuv,
+            start: Instant::now(),
+        })
+    }
+}
+
+impl FrameSource for CameraSource {
+    fn resolution(&self) -> (u32, u32) {
+        let res = self.cam.resolution();
+        (res.width(), res.height())
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        raw_format(self.cam.frame_format(), self.raw_yuv).unwrap_or(PixelFormat::Rgba)
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError> {
+        let frame = self
+            .cam
+            .frame()
+            .map_err(|e| SourceError::Stream(e.to_string()))?;
+        let res = frame.resolution();
+
+        let (format, data) = match raw_format(frame.source_frame_format(), self.raw_yuv) {
+            Some(format) => (format, frame.buffer().to_vec()),
+            None => {
+                let rgba = frame
+                    .decode_image::<RgbAFormat>()
+                    .map_err(|e| SourceError::Decode(e.to_string()))?;
+                (PixelFormat::Rgba, rgba.into_raw())
+            }
+        };
+
+        Ok(Some(Frame {
+            width: res.width(),
+            height: res.height(),
+            format,
+            data,
+            timestamp: self.start.elapsed(),
+        }))
+    }
+}
+
+/// The undecoded layout of `fourcc`, if it can be passed through.
+fn raw_format(fourcc: FrameFormat, raw_yuv: bool) -> Option<PixelFormat> {
+    match fourcc {
+        FrameFormat::YUYV if raw_yuv => Some(PixelFormat::Yuyv),
+        FrameFormat::NV12 if raw_yuv => Some(PixelFormat::Nv12),
+        _ => None,
+    }
+}
+
+/// Resolves a camera by index, `/dev/videoN` path or case insensitive name
+/// substring.
+pub fn find_camera(spec: &str) -> Result<CameraIndex, SourceError> {
+    if let Ok(index) = spec.parse::<u32>() {
+        return Ok(CameraIndex::Index(index));
+    }
+
+    let cameras = query(ApiBackend::Auto).map_err(|e| SourceError::Open(e.to_string()))?;
+    if spec.starts_with("/dev/") {
+        // v4l puts the device node in the description.
+        if let Some(info) = cameras.iter().find(|c| c.description().ends_with(spec)) {
+            return Ok(info.index().clone());
+        }
+        let digits = spec.trim_start_matches(|c: char| !c.is_ascii_digit());
+        return digits
+            .parse()
+            .map(CameraIndex::Index)
+            .map_err(|_| SourceError::Open(format!("no camera at {spec}")));
+    }
+
+    let needle = spec.to_lowercase();
+    let mut matches = cameras
+        .iter()
+        .filter(|c| c.human_name().to_lowercase().contains(&needle));
+    let info = matches
+        .next()
+        .ok_or_else(|| SourceError::Open(format!("no camera matching '{spec}'")))?;
+    if matches.next().is_some() {
+        info!(
+            "several cameras match '{spec}', using {}",
+            info.human_name()
+        );
+    }
+    Ok(info.index().clone())
+}
+
+/// Prints every camera with the formats it supports.
+pub fn list_cameras() {
+    let cameras = match query(ApiBackend::Auto) {
+        Ok(cameras) => cameras,
+        Err(e) => {
+            eprintln!("ERROR: failed to query cameras: {e}");
+            return;
+        }
+    };
+    if cameras.is_empty() {
+        println!("no cameras found");
+    }
+
+    for info in cameras {
+        println!(
+            "[{}] {} ({})",
+            info.index(),
+            info.human_name(),
+            info.description()
+        );
+
+        let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::None);
+        let mut formats = match Camera::new(info.index().clone(), format)
+            .and_then(|mut cam| cam.compatible_camera_formats())
+        {
+            Ok(formats) => formats,
+            Err(e) => {
+                println!("    could not read formats: {e}");
+                continue;
+            }
+        };
+        formats.sort_by_key(|f| (f.format(), f.width(), f.height(), f.frame_rate()));
+
+        // One line per format and resolution, listing the frame rates.
+        for group in
+            formats.chunk_by(|a, b| a.format() == b.format() && a.resolution() == b.resolution())
+        {
+            let rates: Vec<String> = group.iter().map(|f| f.frame_rate().to_string()).collect();
+            println!(
+                "    {:<6} {:>4}x{:<4} @ {} fps",
+                group[0].format(),
+                group[0].width(),
+                group[0].height(),
+                rates.join("/")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(w: u32, h: u32, fps: u32, fourcc: FrameFormat) -> CameraFormat {
+        CameraFormat::new(Resolution::new(w, h), fourcc, fps)
+    }
+
+    fn formats() -> Vec<CameraFormat> {
+        vec![
+            format(640, 480, 30, FrameFormat::YUYV),
+            format(1280, 720, 10, FrameFormat::YUYV),
+            format(640, 480, 60, FrameFormat::MJPEG),
+            format(1280, 720, 30, FrameFormat::MJPEG),
+            format(1920, 1080, 30, FrameFormat::MJPEG),
+        ]
+    }
+
+    fn request(
+        resolution: Option<(u32, u32)>,
+        fps: Option<u32>,
+        fourcc: Option<FrameFormat>,
+        fallback: Fallback,
+    ) -> FormatRequest {
+        FormatRequest {
+            resolution,
+            fps,
+            fourcc,
+            fallback,
+        }
+    }
+
+    #[test]
+    fn exact_match_wins() {
+        let r = request(Some((1280, 720)), Some(30), None, Fallback::HighestFps);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[3]));
+        let r = request(None, None, Some(FrameFormat::MJPEG), Fallback::Closest);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[2]));
+        assert_eq!(r.negotiate(&[]), None);
+    }
+
+    #[test]
+    fn closest_resolution_then_fps() {
+        let r = request(Some((1280, 700)), Some(60), None, Fallback::Closest);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[3]));
+        let r = request(Some((600, 480)), Some(50), None, Fallback::Closest);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[2]));
+    }
+
+    #[test]
+    fn fourcc_is_kept_when_offered() {
+        // MJPEG offers 1280x720@30 exactly, but YUYV is asked for.
+        let r = request(
+            Some((1280, 720)),
+            Some(30),
+            Some(FrameFormat::YUYV),
+            Fallback::Closest,
+        );
+        assert_eq!(r.negotiate(&formats()), Some(formats()[1]));
+        let r = request(
+            None,
+            Some(60),
+            Some(FrameFormat::YUYV),
+            Fallback::HighestFps,
+        );
+        assert_eq!(r.negotiate(&formats()), Some(formats()[0]));
+    }
+
+    #[test]
+    fn missing_fourcc_falls_back_to_any() {
+        let r = request(
+            Some((1920, 1080)),
+            None,
+            Some(FrameFormat::NV12),
+            Fallback::Closest,
+        );
+        assert_eq!(r.negotiate(&formats()), Some(formats()[4]));
+    }
+
+    #[test]
+    fn highest_fps_and_resolution() {
+        let r = request(Some((320, 240)), None, None, Fallback::HighestFps);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[2]));
+        let r = request(Some((320, 240)), None, None, Fallback::HighestResolution);
+        assert_eq!(r.negotiate(&formats()), Some(formats()[4]));
+        // Ties on frame rate go to the larger resolution, and the other way.
+        let r = request(None, Some(25), None, Fallback::HighestFps);
+        let tied = [formats()[0], formats()[3]];
+        assert_eq!(r.negotiate(&tied), Some(formats()[3]));
+        let r = request(None, Some(25), None, Fallback::HighestResolution);
+        let tied = [formats()[1], formats()[3]];
+        assert_eq!(r.negotiate(&tied), Some(formats()[3]));
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{Frame, FrameSource, PixelFormat, SourceError};
+
+/// Plays a still image, or a directory of numbered frames, at a fixed rate.
+pub struct ImageSource {
+    paths: Vec<PathBuf>,
+    fps: f32,
+    looping: bool,
+    resolution: (u32, u32),
+    current: Option<usize>,
+    start: Instant,
+}
+
+impl ImageSource {
+    pub fn open(path: &Path, fps: f32, looping: bool) -> Result<Self, SourceError> {
+        let paths = if path.is_dir() {
+            frame_paths(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        if paths.is_empty() {
+            return Err(SourceError::Open(format!(
+                "no png or jpeg frames in {}",
+                path.display()
+            )));
+        }
+
+        let resolution = image::image_dimensions(&paths[0])
+            .map_err(|e| SourceError::Open(format!("{}: {e}", paths[0].display())))?;
+
+        Ok(Self {
+            paths,
+            fps,
+            looping,
+            resolution,
+            current: None,
+            start: Instant::now(),
+        })
+    }
+}
+
+impl FrameSource for ImageSource {
+    fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::Rgba
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError> {
+        let count = self.paths.len();
+        let mut index = (self.start.elapsed().as_secs_f32() * self.fps) as usize;
+        if self.looping {
+            index %= count;
+        } else {
+            index = index.min(count - 1);
+        }
+        if self.current == Some(index) {
+            return Ok(None);
+        }
+        self.current = Some(index);
+
+        let path = &self.paths[index];
+        let rgba = image::open(path)
+            .map_err(|e| SourceError::Decode(format!("{}: {e}", path.display())))?
+            .into_rgba8();
+        self.resolution = rgba.dimensions();
+
+        Ok(Some(Frame {
+            width: rgba.width(),
+            height: rgba.height(),
+            format: PixelFormat::Rgba,
+            data: rgba.into_raw(),
+            timestamp: Duration::from_secs_f32(index as f32 / self.fps),
+        }))
+    }
+}
+
+/// Image files in `dir` sorted by the number in their file name.
+fn frame_paths(dir: &Path) -> Result<Vec<PathBuf>, SourceError> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| SourceError::Open(format!("{}: {e}", dir.display())))?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg")
+        })
+        .collect();
+    paths.sort_by_key(|path| (frame_number(path), path.clone()));
+
+    Ok(paths)
+}
+
+/// Trailing digits of the file stem, e.g. `frame_0042.png` -> 42.
+fn frame_number(path: &Path) -> u64 {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let digits: String = stem
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse().unwrap_or(0)
+}
@@ -0,0 +1,71 @@
+//! Frame sources that feed the render loop.
+
+use std::fmt;
+use std::time::Duration;
+
+mod camera;
+mod images;
+mod pattern;
+mod threaded;
+mod video;
+
+pub use camera::{CameraSource, Fallback, FormatRequest, find_camera, list_cameras};
+pub use images::ImageSource;
+pub use pattern::{Pattern, PatternSource, no_signal_frame};
+pub use threaded::{CaptureStats, ThreadedSource};
+pub use video::{RawFormat, VideoSource};
+
+/// Memory layout of the bytes in a [`Frame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8 bits per channel, 4 channels, row major.
+    Rgba,
+    /// Packed 4:2:2, `Y0 U Y1 V` for every pair of pixels.
+    Yuyv,
+    /// Full size Y plane followed by a half size interleaved UV plane.
+    Nv12,
+}
+
+/// A single decoded frame.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: Vec<u8>,
+    /// Time since the source was opened.
+    pub timestamp: Duration,
+}
+
+/// Why a source failed.
+#[derive(Clone, Debug)]
+pub enum SourceError {
+    /// The device or file could not be opened.
+    Open(String),
+    /// An open source stopped delivering frames, e.g. the camera was unplugged.
+    Stream(String),
+    /// A frame arrived but could not be decoded. The source is still usable.
+    Decode(String),
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Open(e) => write!(f, "failed to open source: {e}"),
+            SourceError::Stream(e) => write!(f, "source stopped: {e}"),
+            SourceError::Decode(e) => write!(f, "failed to decode frame: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// Anything that can produce frames for the shader.
+pub trait FrameSource {
+    /// Current frame size in pixels.
+    fn resolution(&self) -> (u32, u32);
+
+    fn pixel_format(&self) -> PixelFormat;
+
+    /// Returns the next frame, or `None` if there is no new frame yet.
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError>;
+}
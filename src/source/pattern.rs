@@ -0,0 +1,277 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use super::{Frame, FrameSource, PixelFormat, SourceError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// SMPTE color bars.
+    Bars,
+    /// Circular zone plate, sweeps from DC at the center to Nyquist at the edge.
+    ZonePlate,
+    /// Scrolling 16px checkerboard.
+    Checker,
+    /// Scrolling red, green, blue and grey ramps.
+    Gradient,
+    /// Frame number burned in as large digits.
+    Counter,
+}
+
+impl Pattern {
+    pub const ALL: [(&str, Pattern); 5] = [
+        ("bars", Pattern::Bars),
+        ("zoneplate", Pattern::ZonePlate),
+        ("checker", Pattern::Checker),
+        ("gradient", Pattern::Gradient),
+        ("counter", Pattern::Counter),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|(_, p)| *p)
+    }
+}
+
+/// Procedurally generated test pattern.
+pub struct PatternSource {
+    pattern: Pattern,
+    width: u32,
+    height: u32,
+    fps: f32,
+    index: Option<u64>,
+    start: Instant,
+}
+
+impl PatternSource {
+    pub fn new(pattern: Pattern, (width, height): (u32, u32), fps: f32) -> Self {
+        Self {
+            pattern,
+            width,
+            height,
+            fps,
+            index: None,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl FrameSource for PatternSource {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::Rgba
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError> {
+        let index = (self.start.elapsed().as_secs_f32() * self.fps) as u64;
+        if self.index == Some(index) {
+            return Ok(None);
+        }
+        self.index = Some(index);
+
+        let (w, h) = (self.width, self.height);
+        let mut data = vec![0; (w * h * 4) as usize];
+        match self.pattern {
+            Pattern::Bars => bars(&mut data, w, h),
+            Pattern::ZonePlate => zone_plate(&mut data, w, h, index),
+            Pattern::Checker => checker(&mut data, w, h, index),
+            Pattern::Gradient => gradient(&mut data, w, h, index),
+            Pattern::Counter => counter(&mut data, w, h, index),
+        }
+
+        Ok(Some(Frame {
+            width: w,
+            height: h,
+            format: PixelFormat::Rgba,
+            data,
+            timestamp: Duration::from_secs_f32(index as f32 / self.fps),
+        }))
+    }
+}
+
+/// Dim static with "NO SIGNAL" across the middle, shown while a source is
+/// disconnected. `index` seeds the noise so it can be animated.
+pub fn no_signal_frame((w, h): (u32, u32), index: u64) -> Frame {
+    let mut data = vec![0; (w * h * 4) as usize];
+
+    let mut state = (index as u32).wrapping_mul(0x9e37_79b9) | 1;
+    fill(&mut data, w, h, |_, _| {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let v = (state >> 26) as u8 + 16;
+        [v, v, v]
+    });
+
+    let text = "NO SIGNAL";
+    let scale = (w / (text.len() as u32 * 4 * 2)).max(1);
+    let x = w.saturating_sub(text.len() as u32 * 4 * scale) / 2;
+    let y = h.saturating_sub(5 * scale) / 2;
+    draw_text(&mut data, w, h, x, y, scale, text);
+
+    Frame {
+        width: w,
+        height: h,
+        format: PixelFormat::Rgba,
+        data,
+        timestamp: Duration::ZERO,
+    }
+}
+
+fn fill(data: &mut [u8], w: u32, h: u32, mut color: impl FnMut(u32, u32) -> [u8; 3]) {
+    for y in 0..h {
+        for x in 0..w {
+            let [r, g, b] = color(x, y);
+            let i = ((y * w + x) * 4) as usize;
+            data[i..i + 4].copy_from_slice(&[r, g, b, 255]);
+        }
+    }
+}
+
+fn bars(data: &mut [u8], w: u32, h: u32) {
+    const TOP: [[u8; 3]; 7] = [
+        [191, 191, 191],
+        [191, 191, 0],
+        [0, 191, 191],
+        [0, 191, 0],
+        [191, 0, 191],
+        [191, 0, 0],
+        [0, 0, 191],
+    ];
+    const MIDDLE: [[u8; 3]; 7] = [
+        [0, 0, 191],
+        [19, 19, 19],
+        [191, 0, 191],
+        [19, 19, 19],
+        [0, 191, 191],
+        [19, 19, 19],
+        [191, 191, 191],
+    ];
+    // -I, white, +Q, black, then the pluge below/at/above black.
+    const BOTTOM: [([u8; 3], f32); 8] = [
+        ([0, 33, 76], 5.0 / 4.0),
+        ([255, 255, 255], 5.0 / 4.0),
+        ([50, 0, 106], 5.0 / 4.0),
+        ([19, 19, 19], 5.0 / 4.0),
+        ([9, 9, 9], 1.0 / 3.0),
+        ([19, 19, 19], 1.0 / 3.0),
+        ([29, 29, 29], 1.0 / 3.0),
+        ([19, 19, 19], 1.0),
+    ];
+
+    let bar = w as f32 / 7.0;
+    fill(data, w, h, |x, y| {
+        let fy = y as f32 / h as f32;
+        let col = ((x as f32 / bar) as usize).min(6);
+        if fy < 0.67 {
+            TOP[col]
+        } else if fy < 0.75 {
+            MIDDLE[col]
+        } else {
+            let mut edge = 0.0;
+            for (color, width) in BOTTOM {
+                edge += width * bar;
+                if (x as f32) < edge {
+                    return color;
+                }
+            }
+            BOTTOM[7].0
+        }
+    });
+}
+
+fn zone_plate(data: &mut [u8], w: u32, h: u32, frame: u64) {
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    // Local frequency k*r/PI reaches 0.5 cycles per pixel at the nearest edge.
+    let k = PI * 0.5 / cx.min(cy);
+    let phase = frame as f32 * 0.1;
+    fill(data, w, h, |x, y| {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let v = 0.5 + 0.5 * (k * (dx * dx + dy * dy) + phase).cos();
+        let v = (v * 255.0) as u8;
+        [v, v, v]
+    });
+}
+
+fn checker(data: &mut [u8], w: u32, h: u32, frame: u64) {
+    let offset = frame as u32;
+    fill(data, w, h, |x, y| {
+        if ((x + offset) / 16 + y / 16).is_multiple_of(2) {
+            [255, 255, 255]
+        } else {
+            [0, 0, 0]
+        }
+    });
+}
+
+fn gradient(data: &mut [u8], w: u32, h: u32, frame: u64) {
+    let offset = frame as u32 * 2;
+    fill(data, w, h, |x, y| {
+        let v = (((x + offset) % w) * 255 / w.max(2).saturating_sub(1)) as u8;
+        match y * 4 / h {
+            0 => [v, 0, 0],
+            1 => [0, v, 0],
+            2 => [0, 0, v],
+            _ => [v, v, v],
+        }
+    });
+}
+
+fn counter(data: &mut [u8], w: u32, h: u32, frame: u64) {
+    fill(data, w, h, |_, _| [32, 32, 32]);
+
+    let text = frame.to_string();
+    let scale = (w / (text.len() as u32 * 4 + 1)).min(h / 7).max(1);
+    let x = w.saturating_sub(text.len() as u32 * 4 * scale) / 2;
+    let y = h.saturating_sub(5 * scale) / 2;
+    draw_text(data, w, h, x, y, scale, &text);
+}
+
+/// 3x5 bitmap glyphs, one row per byte with the low three bits used.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        _ => [0; 5],
+    }
+}
+
+/// Draws `text` in white with its top left corner at `x`, `y`.
+fn draw_text(data: &mut [u8], w: u32, h: u32, x: u32, y: u32, scale: u32, text: &str) {
+    for (n, c) in text.chars().enumerate() {
+        let gx = x + n as u32 * 4 * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for py in 0..scale {
+                    for px in 0..scale {
+                        let (dx, dy) = (gx + col * scale + px, y + row as u32 * scale + py);
+                        if dx < w && dy < h {
+                            let i = ((dy * w + dx) * 4) as usize;
+                            data[i..i + 3].copy_from_slice(&[255, 255, 255]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Frame, FrameSource, PixelFormat, SourceError};
+
+/// How long to wait between attempts to reopen a failed source.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// How long to wait after a frame fails to decode, so a broken stream
+/// doesn't spin.
+const DECODE_ERROR_DELAY: Duration = Duration::from_millis(10);
+/// Decode errors are logged at most once per this interval.
+const DECODE_LOG_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Counters for how well capture keeps up with rendering.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureStats {
+    /// Frames delivered by the source.
+    pub captured: u64,
+    /// Frames replaced in the mailbox before the renderer picked them up.
+    pub dropped: u64,
+    /// Rendered frames that had no new input and showed the previous one again.
+    pub duplicated: u64,
+}
+
+struct Mailbox {
+    latest: Mutex<Option<Frame>>,
+    /// Set while the source is down, cleared once it delivers again.
+    error: Mutex<Option<SourceError>>,
+    stop: AtomicBool,
+    captured: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Runs another source on a background thread, so a slow or stalled source
+/// never blocks rendering. Only the most recent frame is kept.
+///
+/// If opening or reading fails the source is dropped and reopened every
+/// [`RECONNECT_INTERVAL`] until it works again, e.g. when a USB camera is
+/// plugged back in.
+pub struct ThreadedSource {
+    mailbox: Arc<Mailbox>,
+    resolution: (u32, u32),
+    pixel_format: PixelFormat,
+    duplicated: u64,
+}
+
+impl ThreadedSource {
+    /// Opens the source with `open` on the capture thread, since camera
+    /// handles can't be moved between threads. Blocks until the first
+    /// attempt has succeeded or failed; `fallback_size` is reported as the
+    /// resolution until a frame arrives if it failed.
+    pub fn spawn<F>(open: F, fallback_size: (u32, u32)) -> Self
+    where
+        F: Fn() -> Result<Box<dyn FrameSource>, SourceError> + Send + 'static,
+    {
+        let mailbox = Arc::new(Mailbox {
+            latest: Mutex::new(None),
+            error: Mutex::new(None),
+            stop: AtomicBool::new(false),
+            captured: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+
+        let (info_tx, info_rx) = mpsc::channel();
+        let shared = mailbox.clone();
+        thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) {
+                    let mut source = match open() {
+                        Ok(source) => source,
+                        Err(e) => {
+                            shared.fail(e);
+                            let _ = info_tx.send(None);
+                            thread::sleep(RECONNECT_INTERVAL);
+                            continue;
+                        }
+                    };
+                    let _ = info_tx.send(Some((source.resolution(), source.pixel_format())));
+
+                    shared.capture(source.as_mut());
+                    // Reading failed, wait as for a failed open so a source
+                    // that opens but can't read isn't reopened in a loop.
+                    drop(source);
+                    if !shared.stop.load(Ordering::Relaxed) {
+                        thread::sleep(RECONNECT_INTERVAL);
+                    }
+                }
+            })
+            .unwrap();
+
+        let (resolution, pixel_format) = info_rx
+            .recv()
+            .ok()
+            .flatten()
+            .unwrap_or((fallback_size, PixelFormat::Rgba));
+
+        Self {
+            mailbox,
+            resolution,
+            pixel_format,
+            duplicated: 0,
+        }
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            captured: self.mailbox.captured.load(Ordering::Relaxed),
+            dropped: self.mailbox.dropped.load(Ordering::Relaxed),
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Why the source is currently down, `None` while frames are flowing.
+    pub fn error(&self) -> Option<SourceError> {
+        self.mailbox.error.lock().unwrap().clone()
+    }
+}
+
+impl Mailbox {
+    fn fail(&self, e: SourceError) {
+        let mut error = self.error.lock().unwrap();
+        if error.is_none() {
+            info!("{e}, retrying every {}s", RECONNECT_INTERVAL.as_secs());
+        }
+        *error = Some(e);
+    }
+
+    /// Reads frames until the source breaks or the mailbox is stopped.
+    fn capture(&self, source: &mut dyn FrameSource) {
+        let mut last_logged: Option<Instant> = None;
+        // Decode errors since the last one logged.
+        let mut unlogged = 0;
+        while !self.stop.load(Ordering::Relaxed) {
+            let frame = match source.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    // File and pattern sources return early when no frame is due.
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                Err(e @ SourceError::Decode(_)) => {
+                    if last_logged.is_none_or(|t| t.elapsed() >= DECODE_LOG_INTERVAL) {
+                        match unlogged {
+                            0 => info!("{e}"),
+                            n => info!("{e} ({n} more decode errors since the last message)"),
+                        }
+                        last_logged = Some(Instant::now());
+                        unlogged = 0;
+                    } else {
+                        unlogged += 1;
+                    }
+                    thread::sleep(DECODE_ERROR_DELAY);
+                    continue;
+                }
+                Err(e) => {
+                    self.fail(e);
+                    return;
+                }
+            };
+
+            if self.error.lock().unwrap().take().is_some() {
+                info!("source reconnected");
+            }
+            self.captured.fetch_add(1, Ordering::Relaxed);
+            if self.latest.lock().unwrap().replace(frame).is_some() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl FrameSource for ThreadedSource {
+    fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    /// Never fails, errors on the capture thread are reported by
+    /// [`ThreadedSource::error`] instead.
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError> {
+        let frame = self.mailbox.latest.lock().unwrap().take();
+        match &frame {
+            Some(f) => {
+                self.resolution = (f.width, f.height);
+                self.pixel_format = f.format;
+            }
+            None => self.duplicated += 1,
+        }
+        Ok(frame)
+    }
+}
+
+impl Drop for ThreadedSource {
+    fn drop(&mut self) {
+        // Not joined, the thread may be stuck waiting on a dead camera.
+        self.mailbox.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::{Frame, FrameSource, PixelFormat, SourceError};
+use crate::convert::{self, Planes};
+
+/// Layout of a raw dump without a header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawFormat {
+    Rgba,
+    Yuyv,
+}
+
+/// Chroma layout of a y4m stream. Only 8 bit streams are supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Chroma {
+    C420,
+    C422,
+    C444,
+    /// 4:4:4 followed by an alpha plane, which is ignored.
+    C444Alpha,
+    Mono,
+}
+
+enum Kind {
+    Y4m { chroma: Chroma, full_range: bool },
+    Raw(RawFormat),
+}
+
+/// Plays an uncompressed YUV4MPEG2 file or a raw RGBA/YUYV dump.
+pub struct VideoSource {
+    reader: BufReader<File>,
+    kind: Kind,
+    width: u32,
+    height: u32,
+    fps: f32,
+    looping: bool,
+    /// Offset of the first frame, where looping seeks back to.
+    data_start: u64,
+    buf: Vec<u8>,
+    index: u64,
+    start: Instant,
+}
+
+impl VideoSource {
+    /// Opens a `.y4m` file. `fps` overrides the rate from the header.
+    pub fn open_y4m(path: &Path, fps: Option<f32>, looping: bool) -> Result<Self, SourceError> {
+        let err = |e: &dyn std::fmt::Display| SourceError::Open(format!("{}: {e}", path.display()));
+
+        let file = File::open(path).map_err(|e| err(&e))?;
+        let mut reader = BufReader::new(file);
+
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| err(&e))?;
+        let mut tokens = header.trim_end().split(' ');
+        if tokens.next() != Some("YUV4MPEG2") {
+            return Err(err(&"not a y4m file"));
+        }
+
+        let (mut width, mut height) = (0, 0);
+        let mut rate = 25.0;
+        let mut chroma = Chroma::C420;
+        let mut full_range = false;
+        for token in tokens {
+            let mut chars = token.chars();
+            let Some(tag) = chars.next() else {
+                continue;
+            };
+            let value = chars.as_str();
+            match tag {
+                'W' => width = value.parse().unwrap_or(0),
+                'H' => height = value.parse().unwrap_or(0),
+                'F' => {
+                    if let Some((num, den)) = value.split_once(':') {
+                        let num: f32 = num.parse().unwrap_or(0.0);
+                        let den: f32 = den.parse().unwrap_or(0.0);
+                        if num > 0.0 && den > 0.0 {
+                            rate = num / den;
+                        }
+                    }
+                }
+                'C' => {
+                    chroma = match value {
+                        "420" | "420jpeg" | "420paldv" | "420mpeg2" => Chroma::C420,
+                        "422" => Chroma::C422,
+                        "444" => Chroma::C444,
+                        "444alpha" => Chroma::C444Alpha,
+                        "mono" => Chroma::Mono,
+                        _ => return Err(err(&format!("unsupported colorspace C{value}"))),
+                    }
+                }
+                'X' if value == "COLORRANGE=FULL" => full_range = true,
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err(err(&"missing frame size"));
+        }
+
+        let data_start = reader.stream_position().map_err(|e| err(&e))?;
+
+        Ok(Self {
+            reader,
+            kind: Kind::Y4m { chroma, full_range },
+            width,
+            height,
+            fps: fps.unwrap_or(rate),
+            looping,
+            data_start,
+            buf: Vec::new(),
+            index: 0,
+            start: Instant::now(),
+        })
+    }
+
+    /// Opens a headerless dump of `width` x `height` frames.
+    pub fn open_raw(
+        path: &Path,
+        format: RawFormat,
+        (width, height): (u32, u32),
+        fps: f32,
+        looping: bool,
+    ) -> Result<Self, SourceError> {
+        let file =
+            File::open(path).map_err(|e| SourceError::Open(format!("{}: {e}", path.display())))?;
+
+        Ok(Self {
+            reader: BufReader::new(file),
+            kind: Kind::Raw(format),
+            width,
+            height,
+            fps,
+            looping,
+            data_start: 0,
+            buf: Vec::new(),
+            index: 0,
+            start: Instant::now(),
+        })
+    }
+
+    fn frame_size(&self) -> usize {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        match self.kind {
+            Kind::Y4m { chroma, .. } => match chroma {
+                Chroma::C420 => w * h + 2 * cw * ch,
+                Chroma::C422 => w * h + 2 * cw * h,
+                Chroma::C444 => w * h * 3,
+                Chroma::C444Alpha => w * h * 4,
+                Chroma::Mono => w * h,
+            },
+            Kind::Raw(RawFormat::Rgba) => w * h * 4,
+            Kind::Raw(RawFormat::Yuyv) => cw * 4 * h,
+        }
+    }
+
+    /// Reads the next frame into `self.buf`.
+    fn read_frame(&mut self) -> io::Result<()> {
+        if let Kind::Y4m { .. } = self.kind {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if !line.starts_with("FRAME") {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "missing FRAME marker",
+                ));
+            }
+        }
+
+        self.buf.resize(self.frame_size(), 0);
+        self.reader.read_exact(&mut self.buf)
+    }
+
+    fn decode(&self) -> Vec<u8> {
+        let (w, h) = (self.width, self.height);
+        let mut rgba = Vec::new();
+        match self.kind {
+            Kind::Y4m { chroma, full_range } => {
+                let (cw, ch) = match chroma {
+                    Chroma::C420 => (w.div_ceil(2), h.div_ceil(2)),
+                    Chroma::C422 => (w.div_ceil(2), h),
+                    Chroma::C444 | Chroma::C444Alpha => (w, h),
+                    Chroma::Mono => {
+                        convert::luma_to_rgba(&self.buf, full_range, &mut rgba);
+                        return rgba;
+                    }
+                };
+                let (luma, chroma) = self.buf.split_at((w * h) as usize);
+                let (u, v) = chroma.split_at((cw * ch) as usize);
+                let v = &v[..(cw * ch) as usize];
+                let planes = Planes {
+                    y: luma,
+                    u,
+                    v,
+                    cw,
+                    ch,
+                };
+                convert::planar_to_rgba(&planes, w, h, full_range, &mut rgba);
+            }
+            Kind::Raw(RawFormat::Rgba) => rgba = self.buf.clone(),
+            Kind::Raw(RawFormat::Yuyv) => convert::yuyv_to_rgba(&self.buf, w, h, &mut rgba),
+        }
+        rgba
+    }
+}
+
+impl FrameSource for VideoSource {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::Rgba
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, SourceError> {
+        let due = (self.start.elapsed().as_secs_f32() * self.fps) as u64;
+        if self.index > due {
+            return Ok(None);
+        }
+
+        // Skip ahead if rendering fell behind, only the last frame is decoded.
+        while self.index <= due {
+            match self.read_frame() {
+                Ok(()) => self.index += 1,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    if self.looping {
+                        self.reader
+                            .seek(SeekFrom::Start(self.data_start))
+                            .map_err(|e| SourceError::Stream(e.to_string()))?;
+                        self.index = 0;
+                        self.start = Instant::now();
+                    } else {
+                        // Hold the last frame.
+                        self.index = u64::MAX;
+                    }
+                    return Ok(None);
+                }
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    return Err(SourceError::Decode(e.to_string()));
+                }
+                Err(e) => return Err(SourceError::Stream(e.to_string())),
+            }
+        }
+
+        Ok(Some(Frame {
+            width: self.width,
+            height: self.height,
+            format: PixelFormat::Rgba,
+            data: self.decode(),
+            timestamp: Duration::from_secs_f32((self.index - 1) as f32 / self.fps),
+        }))
+    }
+}
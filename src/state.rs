@@ -0,0 +1,54 @@
+//! Settings remembered between runs, stored as `key=value` lines in
+//! `$XDG_CONFIG_HOME/shader-cam/state` (or `~/.config/shader-cam/state`).
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct State {
+    values: BTreeMap<String, String>,
+}
+
+fn path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("shader-cam").join("state"))
+}
+
+impl State {
+    /// Loads the saved state, empty if there is none.
+    pub fn load() -> Self {
+        let values = path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        Self { values }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Sets `key` and writes the state back to disk.
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+
+        let Some(path) = path() else {
+            return;
+        };
+        let text: String = self
+            .values
+            .iter()
+            .map(|(k, v)| format!("{k}={v}\n"))
+            .collect();
+        let result = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, text));
+        if let Err(e) = result {
+            info!("failed to save {}: {e}", path.display());
+        }
+    }
+}
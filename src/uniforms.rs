@@ -0,0 +1,131 @@
+//! Uniforms every shader gets, named and laid out like Shadertoy's:
+//!
+//! - `float iTime`: seconds since start
+//! - `float iTimeDelta`: seconds since the previous frame
+//! - `int iFrame`: frames since start
+//! - `vec3 iResolution`: output size of the pass in pixels, z is 1
+//! - `vec2 iInputResolution`: size of `Texture`, the pass input
+//! - `vec4 iDate`: year, month (0 based), day, seconds since midnight, in UTC
+//! - `vec4 iMouse`: xy is the position while a button is held, zw where it
+//!   was clicked, z negative once released and w negative after the first
+//!   frame. Pixels of the image on the screen, with the origin at its
+//!   bottom left.
+//!
+//! RetroArch shaders get their own per pass uniforms as well: `OutputSize`,
+//! `InputSize`, `TextureSize`, `FrameCount`, `FrameDirection` and
+//! `MVPMatrix`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use macroquad::prelude::{
+    Mat4, Material, MouseButton, Rect, UniformDesc, UniformType, Vec2, Vec4, get_frame_time,
+    get_time, is_mouse_button_down, is_mouse_button_pressed, mouse_position, screen_dpi_scale,
+    vec3, vec4,
+};
+
+pub fn descs() -> Vec<UniformDesc> {
+    vec![
+        UniformDesc::new("iTime", UniformType::Float1),
+        UniformDesc::new("iTimeDelta", UniformType::Float1),
+        UniformDesc::new("iFrame", UniformType::Int1),
+        UniformDesc::new("iResolution", UniformType::Float3),
+        UniformDesc::new("iInputResolution", UniformType::Float2),
+        UniformDesc::new("iDate", UniformType::Float4),
+        UniformDesc::new("iMouse", UniformType::Float4),
+        UniformDesc::new("OutputSize", UniformType::Float2),
+        UniformDesc::new("InputSize", UniformType::Float2),
+        UniformDesc::new("TextureSize", UniformType::Float2),
+        UniformDesc::new("FrameCount", UniformType::Int1),
+        UniformDesc::new("FrameDirection", UniformType::Int1),
+        UniformDesc::new("MVPMatrix", UniformType::Mat4),
+    ]
+}
+
+/// What the uniforms of one pass depend on besides the frame.
+pub struct PassInfo {
+    /// Size of the pass output in pixels.
+    pub output: Vec2,
+    /// Size of `Texture`.
+    pub input: Vec2,
+    pub frame_count_mod: Option<u32>,
+    /// Maps the drawn quad to clip space.
+    pub mvp: Mat4,
+}
+
+#[derive(Default)]
+pub struct Uniforms {
+    time: f32,
+    time_delta: f32,
+    frame: u64,
+    date: Vec4,
+    mouse: Vec4,
+}
+
+impl Uniforms {
+    /// Advances to the current frame, call once per frame. `viewport` is
+    /// where the image is in the window.
+    pub fn update(&mut self, frame: u64, viewport: Rect) {
+        self.time = get_time() as f32;
+        self.time_delta = get_frame_time();
+        self.frame = frame;
+        self.date = date(SystemTime::now());
+
+        let (x, y) = mouse_position();
+        let dpi = screen_dpi_scale();
+        let (x, y) = ((x - viewport.x) * dpi, (viewport.bottom() - y) * dpi);
+        let held = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+        if held.iter().any(|&b| is_mouse_button_pressed(b)) {
+            self.mouse = vec4(x, y, x, y);
+        } else if held.iter().any(|&b| is_mouse_button_down(b)) {
+            self.mouse = vec4(x, y, self.mouse.z.abs(), -self.mouse.w.abs());
+        } else {
+            self.mouse.z = -self.mouse.z.abs();
+            self.mouse.w = -self.mouse.w.abs();
+        }
+    }
+
+    /// Sets the uniforms on `material` for the pass described by `pass`.
+    pub fn apply(&self, material: &Material, pass: &PassInfo) {
+        material.set_uniform("iTime", self.time);
+        material.set_uniform("iTimeDelta", self.time_delta);
+        material.set_uniform("iFrame", self.frame as i32);
+        material.set_uniform("iResolution", vec3(pass.output.x, pass.output.y, 1.0));
+        material.set_uniform("iInputResolution", pass.input);
+        material.set_uniform("iDate", self.date);
+        material.set_uniform("iMouse", self.mouse);
+
+        let frame_count = match pass.frame_count_mod {
+            Some(m) if m > 0 => self.frame % m as u64,
+            _ => self.frame,
+        };
+        material.set_uniform("OutputSize", pass.output);
+        material.set_uniform("InputSize", pass.input);
+        material.set_uniform("TextureSize", pass.input);
+        material.set_uniform("FrameCount", frame_count as i32);
+        material.set_uniform("FrameDirection", 1i32);
+        material.set_uniform("MVPMatrix", pass.mvp);
+    }
+}
+
+/// `iDate` for `now`, the civil date from days since the epoch.
+fn date(now: SystemTime) -> Vec4 {
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let days = (secs / 86400.0).floor() as i64;
+    let seconds = secs - days as f64 * 86400.0;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    vec4(year as f32, (month - 1) as f32, day as f32, seconds as f32)
+}
@@ -0,0 +1,214 @@
+//! Gets frames onto the GPU as RGBA textures.
+//!
+//! YUYV and NV12 frames are uploaded as-is, packed four bytes per RGBA8
+//! texel, and unpacked to RGB by a shader pass. This skips the CPU
+//! conversion, which dominates at 1080p. Anything else is converted with
+//! [`crate::convert`] first.
+
+use macroquad::prelude::{
+    Camera2D, DrawTextureParams, FilterMode, Image, Material, MaterialParams, RenderTarget,
+    ShaderSource, Texture2D, UniformDesc, UniformType, Vec2, WHITE, draw_texture_ex,
+    gl_use_default_material, gl_use_material, load_material, render_target, set_camera,
+    set_default_camera, vec2,
+};
+
+use crate::VERTEX_SHADER;
+use crate::convert;
+use crate::source::{Frame, PixelFormat};
+
+const YUV_COMMON: &str = r#"
+#version 100
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 FrameSize;
+
+// Limited range BT.601, must match convert::yuv_to_rgb.
+vec3 YuvToRgb(float y, float u, float v)
+{
+    y = (y * 255.0 - 16.0) / 219.0;
+    u = (u * 255.0 - 128.0) / 224.0;
+    v = (v * 255.0 - 128.0) / 224.0;
+    vec3 rgb = vec3(y + 1.402 * v, y - 0.344136 * u - 0.714136 * v, y + 1.772 * u);
+    return clamp(rgb, 0.0, 1.0);
+}
+"#;
+
+const YUYV_FRAGMENT_SHADER: &str = r#"
+// Each texel holds Y0 U Y1 V for two pixels.
+void main() {
+    float x = floor(uv.x * FrameSize.x);
+    float texel = floor(x / 2.0);
+    float packedWidth = ceil(FrameSize.x / 2.0);
+    vec4 p = texture2D(Texture, vec2((texel + 0.5) / packedWidth, uv.y));
+    float y = mod(x, 2.0) < 0.5 ? p.r : p.b;
+    gl_FragColor = vec4(YuvToRgb(y, p.g, p.a), 1.0);
+}
+"#;
+
+const NV12_FRAGMENT_SHADER: &str = r#"
+// The NV12 buffer is FrameSize.x bytes wide and 1.5 * FrameSize.y rows high,
+// packed into a texture a quarter as wide.
+float ByteAt(float col, float row)
+{
+    float texel = floor(col / 4.0);
+    vec2 st = vec2((texel + 0.5) / (FrameSize.x / 4.0), (row + 0.5) / (FrameSize.y * 1.5));
+    vec4 p = texture2D(Texture, st);
+    float c = col - texel * 4.0;
+    return dot(p, vec4(equal(vec4(c), vec4(0.0, 1.0, 2.0, 3.0))));
+}
+
+void main() {
+    vec2 px = floor(uv * FrameSize);
+    float y = ByteAt(px.x, px.y);
+    float chromaCol = floor(px.x / 2.0) * 2.0;
+    float chromaRow = FrameSize.y + floor(px.y / 2.0);
+    float u = ByteAt(chromaCol, chromaRow);
+    float v = ByteAt(chromaCol + 1.0, chromaRow);
+    gl_FragColor = vec4(YuvToRgb(y, u, v), 1.0);
+}
+"#;
+
+/// Owns the input texture and keeps it up to date with the latest frame.
+pub struct Uploader {
+    gpu_convert: bool,
+    /// What the effect shader samples.
+    output: Texture2D,
+    /// Raw YUV bytes, four per texel.
+    packed: Texture2D,
+    target: Option<RenderTarget>,
+    /// The conversion shaders, `None` if they fail to compile, in which
+    /// case the CPU converts.
+    yuyv: Option<Material>,
+    nv12: Option<Material>,
+}
+
+impl Uploader {
+    pub fn new(width: u32, height: u32, gpu_convert: bool) -> Self {
+        let output = Texture2D::from_image(&Image {
+            bytes: vec![0; (width * height * 4) as usize], // dummy data
+            width: width as u16,
+            height: height as u16,
+        });
+        output.set_filter(FilterMode::Nearest);
+
+        Self {
+            gpu_convert,
+            output,
+            packed: Texture2D::empty(),
+            target: None,
+            yuyv: yuv_material(YUYV_FRAGMENT_SHADER),
+            nv12: yuv_material(NV12_FRAGMENT_SHADER),
+        }
+    }
+
+    /// The latest frame as RGBA.
+    pub fn texture(&self) -> &Texture2D {
+        &self.output
+    }
+
+    pub fn upload(&mut self, frame: Frame) {
+        let (w, h) = (frame.width, frame.height);
+        let packed_size = match frame.format {
+            PixelFormat::Yuyv => Some((w.div_ceil(2), h)),
+            PixelFormat::Nv12 if w % 4 == 0 && h % 2 == 0 => Some((w / 4, h * 3 / 2)),
+            _ => None,
+        };
+
+        let material = match frame.format {
+            PixelFormat::Yuyv => self.yuyv.as_ref(),
+            _ => self.nv12.as_ref(),
+        };
+        match (packed_size, material) {
+            (Some((pw, ph)), Some(material)) if self.gpu_convert => {
+                update_texture(&mut self.packed, pw, ph, &frame.data);
+
+                let target = sized_target(&mut self.target, vec2(w as f32, h as f32));
+
+                set_camera(&target_camera(&target));
+                gl_use_material(material);
+                material.set_uniform("FrameSize", vec2(w as f32, h as f32));
+                draw_texture_ex(
+                    &self.packed,
+                    0.0,
+                    0.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(w as f32, h as f32)),
+                        ..Default::default()
+                    },
+                );
+                gl_use_default_material();
+                set_default_camera();
+
+                self.output = target.texture;
+            }
+            _ => {
+                if self.target.take().is_some() {
+                    self.output = Texture2D::empty();
+                }
+                let rgba = convert::frame_to_rgba(frame);
+                update_texture(&mut self.output, w, h, &rgba);
+            }
+        }
+    }
+}
+
+/// Camera that draws into `target` with the same orientation as the screen,
+/// so row 0 of the target is the top of the image.
+pub fn target_camera(target: &RenderTarget) -> Camera2D {
+    let size = target.texture.size();
+    Camera2D {
+        zoom: vec2(2.0 / size.x, 2.0 / size.y),
+        target: size / 2.0,
+        render_target: Some(target.clone()),
+        ..Default::default()
+    }
+}
+
+/// The target in `slot` if it is `size`, otherwise a new one with nearest
+/// filtering that replaces it.
+pub fn sized_target(slot: &mut Option<RenderTarget>, size: Vec2) -> RenderTarget {
+    match slot {
+        Some(t) if t.texture.size() == size => t.clone(),
+        _ => {
+            let t = render_target(size.x as u32, size.y as u32);
+            t.texture.set_filter(FilterMode::Nearest);
+            *slot = Some(t.clone());
+            t
+        }
+    }
+}
+
+/// Updates `tex` with RGBA `bytes`, recreating it if the size changed.
+fn update_texture(tex: &mut Texture2D, width: u32, height: u32, bytes: &[u8]) {
+    if tex.size() != vec2(width as f32, height as f32) {
+        *tex = Texture2D::from_rgba8(width as u16, height as u16, bytes);
+        tex.set_filter(FilterMode::Nearest);
+    } else {
+        tex.update_from_bytes(width, height, bytes);
+    }
+}
+
+/// Compiles a conversion shader, logging why if it fails.
+fn yuv_material(main: &str) -> Option<Material> {
+    let fragment = format!("{YUV_COMMON}{main}");
+    load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment: &fragment,
+        },
+        MaterialParams {
+            uniforms: vec![UniformDesc::new("FrameSize", UniformType::Float2)],
+            ..Default::default()
+        },
+    )
+    .map_err(|e| info!("converting YUV on the CPU, the shader failed to compile: {e}"))
+    .ok()
+}
@@ -0,0 +1,396 @@
+//! Checks shaders without a GPU using naga.
+//!
+//! naga only reads Vulkan style GLSL 450, so the GLSL ES 100 that gets
+//! compiled is rewritten first: samplers are split into a `texture2D` and a
+//! shared sampler, plain uniforms become globals and varyings lose their
+//! qualifier. Positions in errors are mapped back through the rewrite, any
+//! `#line` directive and any `#include` to the file the line came from.
+
+use std::path::{Path, PathBuf};
+
+use naga::front::glsl::{Frontend, Options};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+use crate::include::{self, Expanded};
+use crate::shader::{self, Layout};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Line in the source with its includes expanded, `None` for code added
+    /// around it.
+    pub line: Option<usize>,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Formats the diagnostic as `path:line:column: error: message`, with
+    /// the path and line of the file in `source` the line came from.
+    pub fn at(&self, source: &Expanded) -> String {
+        match self.line.and_then(|line| source.locate(line)) {
+            Some((path, line)) => format!(
+                "{}:{line}:{}: error: {}",
+                path.display(),
+                self.column,
+                self.message
+            ),
+            None => format!(
+                "{}: error in generated code: {}",
+                source.path().display(),
+                self.message
+            ),
+        }
+    }
+}
+
+const HEADER: &str = "#version 450 core
+#undef __VERSION__
+#define __VERSION__ 100
+layout(set = 0, binding = 0) uniform sampler validate_sampler;
+vec4 validate_texture(texture2D t, vec2 p) { return texture(sampler2D(t, validate_sampler), p); }
+vec4 validate_texture(texture2D t, vec2 p, float bias) { return texture(sampler2D(t, validate_sampler), p, bias); }
+vec4 validate_textureLod(texture2D t, vec2 p, float lod) { return textureLod(sampler2D(t, validate_sampler), p, lod); }
+";
+
+/// Checks shader files and every `.frag`, `.glsl` and `.fs` file under
+/// directories, printing errors. Files without a `main` or `mainImage` are
+/// snippets for `#include` and skipped. Returns whether all shaders passed.
+pub fn check_files(paths: &[PathBuf]) -> bool {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            shader_files(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let (mut failed, mut snippets) = (0, 0);
+    for path in &files {
+        let source = std::fs::read_to_string(path);
+        // Files for #include only make sense inside a shader.
+        if let Ok(source) = &source
+            && !shader::mentions(source, "main")
+            && !shader::mentions(source, "mainImage")
+        {
+            snippets += 1;
+            continue;
+        }
+        let result = source
+            .map_err(|e| format!("{}: error: {e}", path.display()))
+            .and_then(|source| include::expand(&source, Some(path)))
+            .and_then(|source| {
+                let shader = shader::translate(&source.source, &Layout::default())
+                    .map_err(|e| format!("{}: error: {e}", path.display()))?;
+                Ok(check(Stage::Vertex, &shader.vertex)
+                    .into_iter()
+                    .chain(check(Stage::Fragment, &shader.fragment))
+                    .map(|d| d.at(&source))
+                    .collect())
+            });
+        let errors = result.unwrap_or_else(|e| vec![e]);
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        failed += !errors.is_empty() as usize;
+    }
+    println!(
+        "checked {} shaders, {failed} failed, skipped {snippets} without a main",
+        files.len() - snippets
+    );
+    failed == 0 && files.len() > snippets
+}
+
+/// Appends the shader files in `dir` and its subdirectories, sorted.
+fn shader_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|dir| dir.filter_map(|e| Some(e.ok()?.path())).collect())
+        .unwrap_or_default();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            shader_files(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|e| e == "frag" || e == "glsl" || e == "fs")
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Parses and validates `source`, the GLSL ES 100 given to the driver.
+pub fn check(stage: Stage, source: &str) -> Vec<Diagnostic> {
+    let rewrite = Rewrite::new(stage, source);
+    let naga_stage = match stage {
+        Stage::Vertex => naga::ShaderStage::Vertex,
+        Stage::Fragment => naga::ShaderStage::Fragment,
+    };
+    let module = match Frontend::default().parse(&Options::from(naga_stage), &rewrite.out) {
+        Ok(module) => module,
+        Err(errors) => {
+            return errors
+                .errors
+                .iter()
+                .map(|e| rewrite.diagnostic(e.meta.to_range(), e.kind.to_string()))
+                .collect();
+        }
+    };
+
+    // Varyings have no locations, so they would all collide.
+    let flags = ValidationFlags::all() - ValidationFlags::BINDINGS;
+    match Validator::new(flags, Capabilities::all()).validate(&module) {
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            // The innermost cause says what is actually wrong.
+            let mut message = e.as_inner().to_string();
+            let mut cause = std::error::Error::source(e.as_inner());
+            while let Some(c) = cause {
+                message = format!("{message}: {c}");
+                cause = c.source();
+            }
+            let range = e.spans().filter_map(|(span, _)| span.to_range()).last();
+            vec![rewrite.diagnostic(range, message)]
+        }
+    }
+}
+
+fn header(stage: Stage) -> String {
+    match stage {
+        Stage::Vertex => HEADER.to_string(),
+        Stage::Fragment => format!("{HEADER}layout(location = 0) out vec4 validate_FragColor;\n"),
+    }
+}
+
+/// GLSL 450 for naga, with a map from its byte offsets to `source`.
+struct Rewrite<'a> {
+    source: &'a str,
+    out: String,
+    /// Where each run of `out` came from in `source`, sorted by the first
+    /// field. Copied runs map byte for byte, others to their start.
+    map: Vec<(usize, usize, bool)>,
+}
+
+impl<'a> Rewrite<'a> {
+    fn new(stage: Stage, source: &'a str) -> Self {
+        let mut rewrite = Self {
+            source,
+            out: String::new(),
+            map: Vec::new(),
+        };
+        let has_version = source
+            .lines()
+            .any(|l| l.trim_start().starts_with("#version"));
+        if !has_version {
+            rewrite.emit(0, &header(stage));
+        }
+
+        let bytes = source.as_bytes();
+        let mut binding = 1;
+        let mut pos = 0;
+        let mut line_start = true;
+        while pos < bytes.len() {
+            let rest = &source[pos..];
+            let c = bytes[pos];
+            let directive = rest.trim_start_matches([' ', '\t']);
+            if line_start && directive.starts_with("#version") {
+                rewrite.emit(pos, &header(stage));
+                pos += rest.find('\n').unwrap_or(rest.len());
+                continue;
+            }
+            // Parameter labels are strings, which the preprocessor rejects.
+            if line_start && directive.starts_with("#pragma") {
+                pos += rest.find('\n').unwrap_or(rest.len());
+                continue;
+            }
+            line_start = c == b'\n' || (line_start && (c == b' ' || c == b'\t'));
+
+            let skip = if rest.starts_with("//") {
+                rest.find('\n').unwrap_or(rest.len())
+            } else if rest.starts_with("/*") {
+                rest.find("*/").map_or(rest.len(), |i| i + 2)
+            } else if c.is_ascii_alphanumeric() || c == b'_' {
+                rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len())
+            } else {
+                rest.chars().next().map_or(1, char::len_utf8)
+            };
+            let token = &rest[..skip];
+
+            let replacement = match token {
+                "attribute" => Some("in".to_string()),
+                "varying" => Some(if stage == Stage::Vertex { "out" } else { "in" }.to_string()),
+                "gl_FragColor" => Some("validate_FragColor".to_string()),
+                "texture2D" => Some("validate_texture".to_string()),
+                "texture2DLod" => Some("validate_textureLod".to_string()),
+                "sampler2D" => Some("texture2D".to_string()),
+                "uniform" => {
+                    let statement = rest.split(';').next().unwrap_or_default();
+                    let words: Vec<&str> = statement
+                        .split([' ', '\t', '\n', '\r', ','])
+                        .filter(|w| !w.is_empty() && !matches!(*w, "lowp" | "mediump" | "highp"))
+                        .collect();
+                    if words.get(1) == Some(&"sampler2D") {
+                        // Every texture needs a binding of its own.
+                        let mut decls = String::new();
+                        for name in &words[2..] {
+                            decls += &format!(
+                                "layout(set = 0, binding = {binding}) uniform texture2D {name};"
+                            );
+                            binding += 1;
+                        }
+                        let newlines = statement.matches('\n').count();
+                        rewrite.emit(pos, &(decls + &"\n".repeat(newlines)));
+                        pos += statement.len() + 1;
+                        continue;
+                    }
+                    // Values can be plain globals, naga wants them in blocks.
+                    Some(String::new())
+                }
+                _ => None,
+            };
+            match replacement {
+                Some(text) => rewrite.emit(pos, &text),
+                None => rewrite.copy(pos, token),
+            }
+            pos += skip;
+        }
+        rewrite
+    }
+
+    fn emit(&mut self, at: usize, text: &str) {
+        self.map.push((self.out.len(), at, false));
+        self.out += text;
+    }
+
+    fn copy(&mut self, at: usize, text: &str) {
+        match self.map.last() {
+            Some(&(out, from, true)) if from + (self.out.len() - out) == at => {}
+            _ => self.map.push((self.out.len(), at, true)),
+        }
+        self.out += text;
+    }
+
+    /// Offset in `source` of offset `at` in `out`.
+    fn source_offset(&self, at: usize) -> usize {
+        let i = self.map.partition_point(|m| m.0 <= at).saturating_sub(1);
+        match self.map.get(i) {
+            Some(&(out, from, true)) => from + (at - out),
+            Some(&(_, from, false)) => from,
+            None => 0,
+        }
+    }
+
+    fn diagnostic(&self, range: Option<std::ops::Range<usize>>, message: String) -> Diagnostic {
+        let offset = range.map_or(0, |r| self.source_offset(r.start).min(self.source.len()));
+        let before = &self.source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = offset - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Diagnostic {
+            line: file_line(self.source, line),
+            column,
+            message,
+        }
+    }
+}
+
+/// The line of the original file that line `line` of `source` comes from,
+/// following `#line` directives. Lines before the first one were added.
+fn file_line(source: &str, line: usize) -> Option<usize> {
+    let directive = |text: &str| -> Option<usize> {
+        text.trim_start()
+            .strip_prefix("#line")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    };
+    if !source.lines().any(|text| directive(text).is_some()) {
+        return Some(line);
+    }
+    // Number of the line after the one looked at.
+    let mut next = None;
+    for text in source.lines().take(line - 1) {
+        next = directive(text).or(next.map(|n| n + 1));
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_shader_passes() {
+        let source = "#version 100
+precision mediump float;
+varying vec2 uv;
+uniform sampler2D Texture;
+uniform float Amount;
+void main() {
+    gl_FragColor = texture2D(Texture, uv) * Amount;
+}
+";
+        assert_eq!(check(Stage::Fragment, source), Vec::new());
+    }
+
+    #[test]
+    fn error_after_multi_line_sampler() {
+        let source = "#version 100
+precision mediump float;
+varying vec2 uv;
+uniform sampler2D Texture,
+    Texture1;
+void main() {
+    gl_FragColor = texture2D(Texture1, uv) + missing;
+}
+";
+        let diagnostics = check(Stage::Fragment, source);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].line, Some(7));
+        assert_eq!(diagnostics[0].column, 46);
+    }
+
+    #[test]
+    fn error_after_line_directive() {
+        let source = "#version 100
+precision mediump float;
+uniform sampler2D Texture;
+#line 1
+void main() {
+    gl_FragColor = vec4(missing);
+}
+";
+        let diagnostics = check(Stage::Fragment, source);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(diagnostics[0].column, 25);
+    }
+
+    #[test]
+    fn error_in_shadertoy_shader() {
+        let source = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(missing);
+}
+";
+        let fragment = crate::shadertoy::wrap(source, &[]);
+        let diagnostics = check(Stage::Fragment, &fragment);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].line, Some(2));
+    }
+
+    #[test]
+    fn lines_before_line_directive_are_generated() {
+        let source = "a\nb\n#line 10\nc\nd\n";
+        assert_eq!(file_line(source, 1), None);
+        assert_eq!(file_line(source, 4), Some(10));
+        assert_eq!(file_line(source, 5), Some(11));
+        assert_eq!(file_line("a\nb\n", 2), Some(2));
+    }
+}
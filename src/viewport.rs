@@ -0,0 +1,194 @@
+//! Where the image goes in the window, and the resolution the effects run
+//! at.
+
+use macroquad::prelude::*;
+
+use crate::shader::{Layout, Shader};
+use crate::uniforms::{PassInfo, Uniforms};
+use crate::upload::target_camera;
+
+/// How the image is scaled to the window. The rest of the window is black.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    /// As large as fits, keeping the aspect ratio.
+    Fit,
+    /// Covers the window, keeping the aspect ratio and cropping the rest.
+    Fill,
+    /// Covers the window, ignoring the aspect ratio.
+    Stretch,
+    /// The largest whole multiple that fits, or whole fraction if even the
+    /// image itself is too large, for sharp pixels.
+    Integer,
+}
+
+impl Scaling {
+    /// The rectangle of the window an image of size `image` is drawn in,
+    /// centered and in whole pixels. It extends past the window for `Fill`.
+    pub fn viewport(self, image: Vec2, window: Vec2) -> Rect {
+        let image = image.max(Vec2::ONE);
+        let fit = (window.x / image.x).min(window.y / image.y);
+        let size = match self {
+            Scaling::Fit => image * fit,
+            Scaling::Fill => image * (window.x / image.x).max(window.y / image.y),
+            Scaling::Stretch => window,
+            Scaling::Integer if fit >= 1.0 => image * fit.floor(),
+            Scaling::Integer => image / (1.0 / fit).ceil(),
+        };
+        let size = size.round().max(Vec2::ONE);
+        let offset = ((window - size) / 2.0).round();
+        Rect::new(offset.x, offset.y, size.x, size.y)
+    }
+}
+
+const DOWNSAMPLE: &str = include_str!("shaders/downsample.frag");
+const SHARP_BILINEAR: &str = include_str!("shaders/sharp_bilinear.frag");
+
+/// Filter for scaling the output up to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Upscale {
+    Nearest,
+    Linear,
+    /// Nearest to the largest whole multiple, linear for the rest.
+    SharpBilinear,
+}
+
+/// Fixed resolution the effects run at, see `--resolution`.
+#[derive(Clone, Copy, Debug)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+    /// The effects render at this multiple of the size.
+    pub scale: u32,
+}
+
+/// Gets the inputs to the resolution the effects run at, and their output
+/// into the window.
+pub struct Output {
+    scaling: Scaling,
+    resolution: Option<Resolution>,
+    upscale: Upscale,
+    downsample: Shader,
+    sharp: Shader,
+    /// One per input, at the fixed resolution.
+    targets: Vec<RenderTarget>,
+}
+
+impl Output {
+    pub fn new(scaling: Scaling, resolution: Option<Resolution>, upscale: Upscale) -> Self {
+        Self {
+            scaling,
+            resolution,
+            upscale,
+            downsample: Shader::builtin(DOWNSAMPLE, &[], &Layout::default()),
+            sharp: Shader::builtin(SHARP_BILINEAR, &[], &Layout::default()),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Size of the output of the effects with a fixed resolution.
+    pub fn size(&self) -> Option<Vec2> {
+        self.resolution
+            .map(|r| vec2((r.width * r.scale) as f32, (r.height * r.scale) as f32))
+    }
+
+    /// Multiple of the fixed resolution the first pass renders at.
+    pub fn scale(&self) -> u32 {
+        self.resolution.map_or(1, |r| r.scale)
+    }
+
+    /// The part of the window the image goes in, for a first input of size
+    /// `input`. Placed in whole pixels of high DPI screens too, but in the
+    /// units of the window like everything drawn.
+    pub fn viewport(&self, input: Vec2) -> Rect {
+        let dpi = screen_dpi_scale();
+        let window = vec2(screen_width(), screen_height()) * dpi;
+        let rect = self.scaling.viewport(self.size().unwrap_or(input), window);
+        Rect::new(rect.x / dpi, rect.y / dpi, rect.w / dpi, rect.h / dpi)
+    }
+
+    /// Scales `inputs` to the fixed resolution, if there is one, placed in it
+    /// the way the scaling mode places the image in the window.
+    pub fn downsample(&mut self, inputs: &[Texture2D], uniforms: &Uniforms) -> Vec<Texture2D> {
+        let Some(r) = self.resolution else {
+            return inputs.to_vec();
+        };
+        let size = vec2(r.width as f32, r.height as f32);
+        if self.targets.len() != inputs.len() {
+            self.targets = inputs
+                .iter()
+                .map(|_| {
+                    let t = render_target(r.width, r.height);
+                    t.texture.set_filter(FilterMode::Nearest);
+                    t
+                })
+                .collect();
+        }
+
+        for (input, target) in inputs.iter().zip(&self.targets) {
+            let camera = target_camera(target);
+            set_camera(&camera);
+            clear_background(BLACK);
+            input.set_filter(FilterMode::Linear);
+            let rect = self.scaling.viewport(input.size(), size);
+            let info = PassInfo {
+                output: rect.size(),
+                input: input.size(),
+                frame_count_mod: None,
+                mvp: camera.matrix(),
+            };
+            self.downsample.apply(uniforms, &info);
+            draw_texture_ex(
+                input,
+                rect.x,
+                rect.y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(rect.size()),
+                    ..Default::default()
+                },
+            );
+            gl_use_default_material();
+        }
+        set_default_camera();
+        self.targets.iter().map(|t| t.texture.clone()).collect()
+    }
+
+    /// Draws `output` into `viewport` on the screen with the upscale filter.
+    pub fn present(&self, output: &Texture2D, uniforms: &Uniforms, viewport: Rect) {
+        set_default_camera();
+        clear_background(BLACK);
+        output.set_filter(match self.upscale {
+            Upscale::Nearest => FilterMode::Nearest,
+            Upscale::Linear | Upscale::SharpBilinear => FilterMode::Linear,
+        });
+        if self.upscale == Upscale::SharpBilinear {
+            let (w, h) = (screen_width(), screen_height());
+            let info = PassInfo {
+                output: pixels(viewport),
+                input: output.size(),
+                frame_count_mod: None,
+                mvp: Mat4::orthographic_rh_gl(0.0, w, h, 0.0, -1.0, 1.0),
+            };
+            self.sharp.apply(uniforms, &info);
+        }
+        draw_texture_ex(
+            output,
+            viewport.x,
+            viewport.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(viewport.size()),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+        // Passes read their targets with nearest filtering.
+        output.set_filter(FilterMode::Nearest);
+    }
+}
+
+/// Size of `rect` in pixels of the screen, which are smaller than the units
+/// of the window on high DPI screens.
+pub fn pixels(rect: Rect) -> Vec2 {
+    rect.size() * screen_dpi_scale()
+}